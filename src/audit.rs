@@ -0,0 +1,185 @@
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::os::unix::fs::OpenOptionsExt;
+use std::sync::{Arc, RwLock};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::Instant;
+use chrono::Local;
+use fuser::{Errno, Request};
+use log::error;
+use crate::control::is_control_file;
+use crate::disks::{disk_device_path, is_disk_device};
+use crate::fuse::AsmFS;
+use crate::policy::Requester;
+use crate::stats::errno_name;
+
+/*
+ * Audit trail (-o audit_log=PATH): one line per open() and release() of an ASM file or disk
+ * device, for example
+ *
+ *   2026-10-18T09:14:03.512+02:00 event=open uid=1001 gid=1001 pid=4242 comm="rman" path="+DATA/ORCL/DATAFILE/USERS.259.1" file_number=259 mirror=0 bytes=0 duration_ms=3 result=ok
+ *   2026-10-18T09:14:09.870+02:00 event=release uid=1001 gid=1001 pid=4242 comm="rman" path="+DATA/ORCL/DATAFILE/USERS.259.1" file_number=259 mirror=0 bytes=5251072 duration_ms=6358 result=ok
+ *
+ * open lines are written for refused opens too (result=EACCES, ENOENT, ...) and report how long
+ * open() took. release lines repeat the process that opened the file and report the bytes read
+ * through that handle and how long it was open. Files opened with --no-raw have no file_number
+ * or mirror ("-"). Files in /.asmfs/ other than disk devices are not audited.
+ *
+ * Lines are formatted on the calling thread and passed over a channel to a writer thread, so a
+ * slow log file never holds up a read.
+ */
+
+pub struct AuditLog {
+    sender: Sender<String>,
+    sessions: RwLock<HashMap<(u64, u64), Arc<AuditSession>>>, // (ino, fh) => open handle
+}
+
+// the file as reported in the log; path is relative to the mount point for disk devices
+pub struct AuditedFile {
+    pub path: String,
+    pub file_number: Option<u32>,
+    pub mirror: Option<u8>,
+}
+
+// what an open() line reported, plus the running byte count for the release line
+struct AuditSession {
+    uid: u32,
+    gid: u32,
+    pid: u32,
+    comm: String,
+    file: AuditedFile,
+    opened: Instant,
+    bytes: AtomicU64,
+}
+
+impl AuditLog {
+    pub fn open(path: &str) -> Result<AuditLog, String> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .mode(0o640)
+            .open(path)
+            .map_err(|e| format!("Unable to open audit log {path}: {e}"))?;
+
+        let (sender, receiver) = mpsc::channel();
+
+        thread::Builder::new()
+            .name("asmfs-audit".to_string())
+            .spawn(move || write_lines(file, receiver))
+            .map_err(|e| format!("Unable to start audit log writer: {e}"))?;
+
+        Ok(AuditLog { sender, sessions: RwLock::new(HashMap::new()) })
+    }
+
+    // `result` is the file handle given to the kernel, or why open() was refused
+    pub fn opened(&self, requester: &Requester, ino: u64, file: AuditedFile, started: Instant, result: Result<u64, Errno>) {
+        let session = AuditSession {
+            uid: requester.uid,
+            gid: requester.gid,
+            pid: requester.pid,
+            comm: process_name(requester.pid),
+            file,
+            opened: Instant::now(),
+            bytes: AtomicU64::new(0),
+        };
+
+        self.send(&session, "open", started, result.map(|_| ()));
+
+        if let Ok(fh) = result {
+            self.sessions.write().unwrap().insert((ino, fh), Arc::new(session));
+        }
+    }
+
+    pub fn read(&self, ino: u64, fh: u64, bytes: usize) {
+        if let Some(session) = self.sessions.read().unwrap().get(&(ino, fh)) {
+            session.bytes.fetch_add(bytes as u64, Ordering::Relaxed);
+        }
+    }
+
+    pub fn released(&self, ino: u64, fh: u64, result: Result<(), Errno>) {
+        let session = self.sessions.write().unwrap().remove(&(ino, fh));
+
+        if let Some(session) = session {
+            self.send(&session, "release", session.opened, result);
+        }
+    }
+
+    fn send(&self, session: &AuditSession, event: &str, since: Instant, result: Result<(), Errno>) {
+        let line = format!(
+            "{} event={} uid={} gid={} pid={} comm={:?} path={:?} file_number={} mirror={} bytes={} duration_ms={} result={}\n",
+            Local::now().format("%Y-%m-%dT%H:%M:%S%.3f%:z"),
+            event,
+            session.uid,
+            session.gid,
+            session.pid,
+            session.comm,
+            session.file.path,
+            session.file.file_number.map(|n| n.to_string()).unwrap_or_else(|| "-".to_string()),
+            session.file.mirror.map(|n| n.to_string()).unwrap_or_else(|| "-".to_string()),
+            session.bytes.load(Ordering::Relaxed),
+            since.elapsed().as_millis(),
+            result.map(|_| "ok".to_string()).unwrap_or_else(|errno| errno_name(errno.code())),
+        );
+
+        // the writer thread only stops when the channel is closed, so this cannot fail
+        let _ = self.sender.send(line);
+    }
+}
+
+// flushes whenever the channel runs dry, so the log is current without a write per line
+fn write_lines(file: File, receiver: Receiver<String>) {
+    let mut out = BufWriter::new(file);
+
+    while let Ok(line) = receiver.recv() {
+        let mut result = out.write_all(line.as_bytes());
+
+        while let Ok(line) = receiver.try_recv() {
+            result = result.and_then(|_| out.write_all(line.as_bytes()));
+        }
+
+        if let Err(e) = result.and_then(|_| out.flush()) {
+            error!("Unable to write audit log: {}", e);
+        }
+    }
+}
+
+fn process_name(pid: u32) -> String {
+    fs::read_to_string(format!("/proc/{}/comm", pid))
+        .map(|comm| comm.trim_end().to_string())
+        .unwrap_or_else(|_| "?".to_string())
+}
+
+impl AsmFS {
+    pub(crate) fn audit_open(&self, audit: &AuditLog, req: &Request, ino: u64, started: Instant, result: Result<u64, Errno>) {
+        if is_control_file(ino) {
+            return;
+        }
+
+        let requester = Requester { uid: req.uid(), gid: req.gid(), pid: req.pid() };
+
+        if is_disk_device(ino) {
+            let file = AuditedFile { path: disk_device_path(ino), file_number: None, mirror: None };
+            audit.opened(&requester, ino, file, started, result);
+            return;
+        }
+
+        let path = match self.asm_path(ino) {
+            Ok(path) => path.unwrap_or_default(),
+            Err(e) => format!("(unresolved: {})", e),
+        };
+
+        let (file_number, mirror) = match result {
+            Ok(fh) if self.config.use_raw => {
+                let file_number = self.handles_raw.read().unwrap().get(&fh).map(|handle| handle.file_number);
+                (file_number, Some(self.config.mirror))
+            }
+            _ if self.config.use_raw => (None, Some(self.config.mirror)),
+            _ => (None, None),
+        };
+
+        audit.opened(&requester, ino, AuditedFile { path, file_number, mirror }, started, result);
+    }
+}
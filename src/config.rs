@@ -0,0 +1,341 @@
+use std::fmt::Write;
+use std::fs;
+use std::io::ErrorKind;
+use std::os::unix::fs::PermissionsExt;
+use toml::{Table, Value};
+use crate::credentials;
+
+/**
+ * Effective mount configuration, resolved in main() from the config file, `-o` options and
+ * command line flags.
+ * Read-only once the filesystem is constructed.
+ */
+pub struct MountConfig {
+    pub config_file: Option<String>,        // see load_config_file()
+    pub mount_point: String,
+    pub connection_string: Option<String>,
+    pub failover_conns: Vec<String>,        // host:port/service or alias of other instances, see OracleConnection::connect()
+    pub use_raw: bool,
+    pub hybrid: bool,                       // raw reads fall back to DBMS_DISKGROUP for disks that cannot be opened
+    pub magic: bool,
+    pub mirror: u8,
+    pub threads: usize,
+    pub owner_uid: u32,
+    pub owner_gid: u32,
+    pub allow_root: bool,
+    pub allow_other: bool,
+    pub auto_unmount: bool,
+    pub daemon: bool,
+    pub log_file: Option<String>,
+    pub diskgroups: Option<Vec<String>>,    // upper-case names without '+'; None means all diskgroups
+    pub root: Option<String>,               // "+DATA/ORCL/DATAFILE"; None means the list of diskgroups
+    pub include: Vec<String>,               // path patterns, see filter.rs
+    pub exclude: Vec<String>,
+    pub filter_file: Option<String>,
+    pub ignore_case: bool,
+    pub relative_links: bool,               // readlink returns paths relative to the link's directory
+    pub hard_links: bool,                   // user aliases are shown as the file itself, not as symlinks
+    pub hide_omf: bool,                     // user aliases are regular files, system names of such files are hidden
+    pub file_mode: u32,                     // before umask; see permissions.rs
+    pub dir_mode: u32,
+    pub umask: u32,
+    pub asm_acl: bool,                      // owner, group and mode of files from ASM File Access Control
+    pub policy_file: Option<String>,        // per-user access policy, see policy.rs
+    pub audit_log: Option<String>,          // one line per open/release, see audit.rs
+    pub max_bps: u64,                       // raw read limits, 0 means unlimited; see throttle.rs
+    pub max_iops: u64,
+    pub throttle_file: Option<String>,
+    pub control_socket: Option<String>,     // see ctl.rs
+    pub metrics: Option<String>,            // host:port or unix:PATH, see metrics.rs
+    pub sid: Option<String>,                // --sid/--oracle-home, see oraenv.rs
+    pub oracle_home: Option<String>,
+    pub wait_for_asm: Option<String>,       // timeout as given, "" waits forever; see wait.rs
+    pub run_as: Option<String>,             // see privileges.rs
+    pub confine: bool,
+}
+
+impl MountConfig {
+
+    // rendered as /.asmfs/config, one `key=value` per line
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+
+        let _ = writeln!(out, "config={}", self.config_file.as_deref().unwrap_or(""));
+        let _ = writeln!(out, "mountpoint={}", self.mount_point);
+        let _ = writeln!(out, "conn={}", self.connection_string.as_deref().map(mask_password).unwrap_or_else(|| "(local, / as sysasm)".to_string()));
+        for target in &self.failover_conns {
+            let _ = writeln!(out, "failover_conn={}", target);
+        }
+        let _ = writeln!(out, "raw={}", self.use_raw);
+        let _ = writeln!(out, "hybrid={}", self.hybrid);
+        let _ = writeln!(out, "magic={}", self.magic);
+        let _ = writeln!(out, "mirror={}", self.mirror);
+        let _ = writeln!(out, "threads={}", self.threads);
+        let _ = writeln!(out, "uid={}", self.owner_uid);
+        let _ = writeln!(out, "gid={}", self.owner_gid);
+        let _ = writeln!(out, "allow_root={}", self.allow_root);
+        let _ = writeln!(out, "allow_other={}", self.allow_other);
+        let _ = writeln!(out, "auto_unmount={}", self.auto_unmount);
+        let _ = writeln!(out, "daemon={}", self.daemon);
+        let _ = writeln!(out, "log_file={}", self.log_file.as_deref().unwrap_or(""));
+        let _ = writeln!(out, "diskgroups={}", self.diskgroups.as_ref().map(|dgs| dgs.join(":")).unwrap_or_default());
+        let _ = writeln!(out, "root={}", self.root.as_deref().unwrap_or(""));
+        for pattern in &self.include {
+            let _ = writeln!(out, "include={}", pattern);
+        }
+        for pattern in &self.exclude {
+            let _ = writeln!(out, "exclude={}", pattern);
+        }
+        let _ = writeln!(out, "ignore_case={}", self.ignore_case);
+        let _ = writeln!(out, "relative_links={}", self.relative_links);
+        let _ = writeln!(out, "hard_links={}", self.hard_links);
+        let _ = writeln!(out, "hide_omf={}", self.hide_omf);
+        let _ = writeln!(out, "file_mode={:04o}", self.file_mode);
+        let _ = writeln!(out, "dir_mode={:04o}", self.dir_mode);
+        let _ = writeln!(out, "umask={:04o}", self.umask);
+        let _ = writeln!(out, "asm_acl={}", self.asm_acl);
+        let _ = writeln!(out, "policy_file={}", self.policy_file.as_deref().unwrap_or(""));
+        let _ = writeln!(out, "filter_file={}", self.filter_file.as_deref().unwrap_or(""));
+        let _ = writeln!(out, "audit_log={}", self.audit_log.as_deref().unwrap_or(""));
+        let _ = writeln!(out, "max_bps={}", self.max_bps);
+        let _ = writeln!(out, "max_iops={}", self.max_iops);
+        let _ = writeln!(out, "throttle_file={}", self.throttle_file.as_deref().unwrap_or(""));
+        let _ = writeln!(out, "control_socket={}", self.control_socket.as_deref().unwrap_or(""));
+        let _ = writeln!(out, "metrics={}", self.metrics.as_deref().unwrap_or(""));
+        let _ = writeln!(out, "sid={}", self.sid.as_deref().unwrap_or(""));
+        let _ = writeln!(out, "oracle_home={}", self.oracle_home.as_deref().unwrap_or(""));
+        let _ = writeln!(out, "wait_for_asm={}", match self.wait_for_asm.as_deref() {
+            Some("") => "forever",
+            Some(timeout) => timeout,
+            None => "",
+        });
+        let _ = writeln!(out, "run_as={}", self.run_as.as_deref().unwrap_or(""));
+        let _ = writeln!(out, "confine={}", self.confine);
+
+        out
+    }
+}
+
+// user/pass@host:port/service => user/***@host:port/service
+fn mask_password(conn: &str) -> String {
+    match conn.split_once('@') {
+        Some((user_pass, after_at)) => match user_pass.split_once('/') {
+            Some(("", _)) => conn.to_string(), // /@alias, a wallet
+            Some((user, _)) => format!("{user}/***@{after_at}"),
+            None => conn.to_string(),
+        },
+        None => conn.to_string(),
+    }
+}
+
+/*
+ * /etc/asmfs.toml (or --config / -o config=): the same options as -o, as TOML. Top-level keys
+ * apply to every mount, a [mount."/mnt/asmfs"] section to that mount point only:
+ *
+ *   allow_other = true
+ *   max_bps = "200M"
+ *
+ *   [mount."/mnt/asmfs"]
+ *   conn_file = "/etc/asmfs/data.conn"
+ *   diskgroups = ["DATA", "FRA"]
+ *   root = "+DATA/ORCL"
+ *
+ * `true` is an option without a value, `false` leaves it out, and an array repeats the option.
+ * The options are placed before those of -o and the command line, which therefore win.
+ */
+
+pub const DEFAULT_CONFIG_FILE: &str = "/etc/asmfs.toml";
+
+// every option a config file or -o may set; anything else is a typo
+pub const CONFIG_FILE_KEYS: &[&str] = &[
+    "conn", "conn_file", "failover_conn", "sid", "oracle_home", "wait_for_asm",
+    "no-raw", "no-magic", "mirror", "threads", "daemon", "log-file", "auto_unmount", "auto-unmount",
+    "allow_root", "allow_other", "default_permissions", "uid", "gid", "file_mode", "dir_mode", "umask", "asm_acl",
+    "diskgroups", "root", "include", "exclude", "filter_file", "ignore_case", "ignore-case",
+    "relative_links", "hard_links", "hide_omf", "policy_file", "audit_log",
+    "max_bps", "max_iops", "throttle_file", "control_socket", "metrics", "run_as", "confine", "hybrid",
+];
+
+/**
+ * Options from the config file for `mount_point`, as (key, value) pairs like those of -o.
+ * A missing default file is no error; a missing file given with --config is.
+ */
+pub fn load_config_file(path: Option<&str>, mount_point: &str) -> Result<Vec<(String, Option<String>)>, String> {
+    let content = match path {
+        Some(path) => fs::read_to_string(path).map_err(|e| format!("Unable to read config file {path}: {e}"))?,
+        None => match fs::read_to_string(DEFAULT_CONFIG_FILE) {
+            Ok(content) => content,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(format!("Unable to read config file {DEFAULT_CONFIG_FILE}: {e}")),
+        },
+    };
+    let path = path.unwrap_or(DEFAULT_CONFIG_FILE);
+
+    let table: Table = content.parse().map_err(|e| format!("{path}: {e}"))?;
+
+    // like --conn-file: a password in a file others can read is not secret anymore
+    if conn_has_password(&table) {
+        let metadata = fs::metadata(path).map_err(|e| format!("Unable to read config file {path}: {e}"))?;
+        if metadata.permissions().mode() & 0o077 != 0 {
+            return Err(format!("{path}: 'conn' contains a password, but the file is accessible by group or others; chmod 600 it or use conn_file"));
+        }
+    }
+
+    let mut options = Vec::new();
+
+    for (key, value) in &table {
+        if key != "mount" {
+            push_option(&mut options, key, value).map_err(|e| format!("{path}: {e}"))?;
+        }
+    }
+
+    let sections = match table.get("mount") {
+        None => return Ok(options),
+        Some(Value::Table(sections)) => sections,
+        Some(_) => return Err(format!("{path}: 'mount' must be a table of [mount.\"/mount/point\"] sections")),
+    };
+
+    for (section, value) in sections {
+        let Value::Table(section_options) = value else {
+            return Err(format!("{path}: [mount.\"{section}\"] must be a table"));
+        };
+
+        if !same_mount_point(section, mount_point) {
+            continue;
+        }
+
+        for (key, value) in section_options {
+            push_option(&mut options, key, value).map_err(|e| format!("{path}: [mount.\"{section}\"]: {e}"))?;
+        }
+    }
+
+    Ok(options)
+}
+
+// in any section, not only in the one of this mount point
+fn conn_has_password(table: &Table) -> bool {
+    let sections: Vec<&Table> = match table.get("mount") {
+        Some(Value::Table(sections)) => sections.values().filter_map(Value::as_table).collect(),
+        _ => Vec::new(),
+    };
+
+    std::iter::once(table)
+        .chain(sections)
+        .filter_map(|options| options.get("conn"))
+        .filter_map(Value::as_str)
+        .any(credentials::has_password)
+}
+
+fn push_option(options: &mut Vec<(String, Option<String>)>, key: &str, value: &Value) -> Result<(), String> {
+    if !CONFIG_FILE_KEYS.contains(&key) {
+        return Err(format!("unknown option '{key}'"));
+    }
+
+    match value {
+        Value::Boolean(true) => options.push((key.to_string(), None)),
+        Value::Boolean(false) => {}
+        Value::String(value) => options.push((key.to_string(), Some(value.clone()))),
+        Value::Integer(value) => options.push((key.to_string(), Some(value.to_string()))),
+        Value::Array(values) => {
+            for value in values {
+                if matches!(value, Value::Array(_) | Value::Table(_)) {
+                    return Err(format!("'{key}' must be a list of strings or numbers"));
+                }
+                push_option(options, key, value)?;
+            }
+        }
+        _ => return Err(format!("'{key}' must be a string, number, boolean or list")),
+    }
+
+    Ok(())
+}
+
+// "/mnt/asmfs/" and "/mnt/./asmfs" are the same mount point
+fn same_mount_point(section: &str, mount_point: &str) -> bool {
+    if section.trim_end_matches('/') == mount_point.trim_end_matches('/') {
+        return true;
+    }
+
+    match (fs::canonicalize(section), fs::canonicalize(mount_point)) {
+        (Ok(section), Ok(mount_point)) => section == mount_point,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load(name: &str, content: &str, mount_point: &str) -> Result<Vec<(String, Option<String>)>, String> {
+        let path = std::env::temp_dir().join(format!("asmfs-config-{}-{}.toml", std::process::id(), name));
+        fs::write(&path, content).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600)).unwrap();
+        let options = load_config_file(path.to_str(), mount_point);
+        fs::remove_file(&path).unwrap();
+        options
+    }
+
+    fn option(key: &str, value: Option<&str>) -> (String, Option<String>) {
+        (key.to_string(), value.map(str::to_string))
+    }
+
+    #[test]
+    fn values_become_options() {
+        let mut options = Vec::new();
+        push_option(&mut options, "allow_other", &Value::Boolean(true)).unwrap();
+        push_option(&mut options, "hide_omf", &Value::Boolean(false)).unwrap();
+        push_option(&mut options, "mirror", &Value::Integer(1)).unwrap();
+        push_option(&mut options, "max_bps", &Value::String("200M".to_string())).unwrap();
+
+        assert_eq!(options, [option("allow_other", None), option("mirror", Some("1")), option("max_bps", Some("200M"))]);
+    }
+
+    #[test]
+    fn lists_repeat_the_option() {
+        let mut options = Vec::new();
+        let list = Value::Array(vec![Value::String("*/ONLINELOG/*".to_string()), Value::String("*.trc".to_string())]);
+        push_option(&mut options, "exclude", &list).unwrap();
+
+        assert_eq!(options, [option("exclude", Some("*/ONLINELOG/*")), option("exclude", Some("*.trc"))]);
+
+        let nested = Value::Array(vec![Value::Array(vec![Value::String("DATA".to_string())])]);
+        assert!(push_option(&mut options, "diskgroups", &nested).is_err());
+        assert!(push_option(&mut options, "mirror", &Value::Float(1.5)).is_err());
+        assert!(push_option(&mut options, "mirrors", &Value::Integer(1)).is_err());
+    }
+
+    #[test]
+    fn mount_section_comes_after_the_top_level() {
+        let content = r#"
+            allow_other = true
+            root = "+FRA"
+
+            [mount."/mnt/asmfs/"]
+            root = "+DATA/ORCL"
+            diskgroups = ["DATA", "FRA"]
+
+            [mount."/mnt/other"]
+            root = "+RECO"
+        "#;
+
+        let options = load("sections", content, "/mnt/asmfs").unwrap();
+        assert_eq!(options, [
+            option("allow_other", None),
+            option("root", Some("+FRA")),
+            option("diskgroups", Some("DATA")),
+            option("diskgroups", Some("FRA")),
+            option("root", Some("+DATA/ORCL")),
+        ]);
+    }
+
+    #[test]
+    fn unknown_keys_are_errors() {
+        let error = load("unknown", "[mount.\"/mnt/asmfs\"]\nmirrors = 1\n", "/mnt/asmfs").unwrap_err();
+        assert!(error.ends_with("[mount.\"/mnt/asmfs\"]: unknown option 'mirrors'"), "{error}");
+    }
+
+    #[test]
+    fn missing_file_given_explicitly_is_an_error() {
+        assert!(load_config_file(Some("/nonexistent/asmfs.toml"), "/mnt/asmfs").is_err());
+    }
+}
@@ -0,0 +1,239 @@
+use std::fmt::Write;
+use std::sync::Arc;
+use std::sync::atomic::Ordering;
+use std::time::UNIX_EPOCH;
+use fuser::{FileAttr, FileType, INodeNo};
+use oracle::{Error, ErrorKind};
+use crate::fuse::AsmFS;
+use crate::inode::{Inode, SYNTHETIC_CONTROL, SYNTHETIC_DISKS, SYNTHETIC_DISK_HEADER};
+use crate::oracle::synthetic_dir_time;
+
+/*
+ * The hidden /.asmfs/ directory. Its read-only files describe the running mount, so operators
+ * can inspect it with `cat` instead of attaching a debugger or raising RUST_LOG.
+ *
+ * File sizes are reported as 0 and the files are opened with FOPEN_DIRECT_IO; the content is
+ * rendered once per open() (the same way procfs does it) and served from that snapshot.
+ */
+
+pub const CONTROL_DIR_NAME: &str = ".asmfs";
+
+const CONTROL_FILES: &[(u32, &str)] = &[ // payload (file id), name
+    (1, "version"),
+    (2, "config"),
+    (3, "stats"),
+    (4, "handles"),
+    (5, "diskgroups"),
+    (6, "throttle"),
+];
+
+pub fn control_dir_ino() -> u64 {
+    Inode::from_synthetic(SYNTHETIC_CONTROL, 0).get_ino()
+}
+
+// files whose content is rendered on open(): the control files and the disk headers
+pub fn is_control_file(ino: u64) -> bool {
+    let inode = Inode::from_ino(ino);
+
+    if !inode.is_synthetic() {
+        return false;
+    }
+
+    match inode.get_synthetic_kind() {
+        SYNTHETIC_CONTROL => inode.get_synthetic_payload() != 0,
+        SYNTHETIC_DISK_HEADER => true,
+        _ => false,
+    }
+}
+
+fn unknown_synthetic(ino: u64) -> Error {
+    Error::new(ErrorKind::Other, format!("asmfs; unknown synthetic inode {}", ino))
+}
+
+pub fn synthetic_attr_of(ino: u64, kind: FileType, size: u64) -> FileAttr {
+    let (perm, nlink) = match kind {
+        FileType::Directory => (0o555, 2),
+        _ => (0o444, 1),
+    };
+
+    let time = synthetic_dir_time();
+    FileAttr {
+        ino: INodeNo(ino),
+        size,
+        blocks: size.div_ceil(512),
+        atime: UNIX_EPOCH,
+        mtime: time,
+        ctime: time,
+        crtime: UNIX_EPOCH,
+        kind,
+        perm,
+        nlink,
+        uid: 0,
+        gid: 0,
+        rdev: 0,
+        flags: 0,
+        blksize: 512,
+    }
+}
+
+impl AsmFS {
+    pub(crate) fn synthetic_attr(&self, ino: u64) -> Result<FileAttr, Error> {
+        let inode = Inode::from_ino(ino);
+
+        match (inode.get_synthetic_kind(), inode.get_synthetic_payload()) {
+            (SYNTHETIC_CONTROL, 0) => Ok(synthetic_attr_of(ino, FileType::Directory, 0)),
+            (SYNTHETIC_CONTROL, id) if CONTROL_FILES.iter().any(|(file_id, _)| *file_id == id) => Ok(synthetic_attr_of(ino, FileType::RegularFile, 0)),
+            (SYNTHETIC_CONTROL, _) => Err(unknown_synthetic(ino)),
+            _ => self.disks_attr(ino),
+        }
+    }
+
+    pub(crate) fn synthetic_parent_ino(&self, ino: u64) -> Result<INodeNo, Error> {
+        let inode = Inode::from_ino(ino);
+
+        match inode.get_synthetic_kind() {
+            SYNTHETIC_CONTROL if inode.get_synthetic_payload() == 0 => Ok(INodeNo(1)),
+            SYNTHETIC_CONTROL => Ok(INodeNo(control_dir_ino())),
+            SYNTHETIC_DISKS if inode.get_synthetic_payload() == 0 => Ok(INodeNo(control_dir_ino())),
+            _ => Ok(INodeNo(self.disks_parent_ino(ino))),
+        }
+    }
+
+    pub(crate) fn synthetic_children(&self, ino: u64) -> Result<Vec<(u64, FileType, String)>, Error> {
+        let inode = Inode::from_ino(ino);
+
+        match (inode.get_synthetic_kind(), inode.get_synthetic_payload()) {
+            (SYNTHETIC_CONTROL, 0) => {
+                let mut children: Vec<(u64, FileType, String)> = CONTROL_FILES
+                    .iter()
+                    .map(|(id, name)| (Inode::from_synthetic(SYNTHETIC_CONTROL, *id).get_ino(), FileType::RegularFile, name.to_string()))
+                    .collect();
+                children.push((Inode::from_synthetic(SYNTHETIC_DISKS, 0).get_ino(), FileType::Directory, "disks".to_string()));
+                Ok(children)
+            }
+            (SYNTHETIC_CONTROL, _) => Err(unknown_synthetic(ino)),
+            _ => self.disks_children(ino),
+        }
+    }
+
+    pub(crate) fn synthetic_lookup(&self, parent_ino: u64, name: &str) -> Result<FileAttr, Error> {
+        let child = self
+            .synthetic_children(parent_ino)?
+            .into_iter()
+            .find(|(_, _, child_name)| child_name == name)
+            .ok_or_else(|| Error::new(ErrorKind::Other, format!("asmfs; '{}' not found in synthetic inode {}", name, parent_ino)))?;
+
+        self.synthetic_attr(child.0)
+    }
+
+    pub(crate) fn render_control_file(&self, ino: u64) -> Result<String, Error> {
+        let inode = Inode::from_ino(ino);
+
+        if inode.get_synthetic_kind() == SYNTHETIC_DISK_HEADER {
+            return self.render_disk_header(ino);
+        }
+
+        match inode.get_synthetic_payload() {
+            1 => Ok(self.render_version()),
+            2 => Ok(self.config.render()),
+            3 => Ok(self.render_stats()),
+            4 => Ok(self.render_handles()),
+            5 => self.render_diskgroups(),
+            6 => Ok(self.throttle.render()),
+            _ => Err(unknown_synthetic(ino)),
+        }
+    }
+
+    // the counters of stats.rs and those of the caches, which live with what they cache
+    fn render_stats(&self) -> String {
+        let mut out = self.stats.render();
+
+        // ASM metadata itself is not cached by asmfs, only by the kernel for TTL and TTL_DIR (fuse.rs)
+        let _ = writeln!(out);
+        let _ = writeln!(out, "{:<16} {:>12} {:>12}", "cache", "hits", "misses");
+        let _ = writeln!(out, "{:<16} {:>12} {:>12}", "asm_user_group", self.id_cache.hits.load(Ordering::Relaxed), self.id_cache.misses.load(Ordering::Relaxed));
+
+        out
+    }
+
+    fn render_version(&self) -> String {
+        // the instance changes with a failover, see --failover-conn
+        let instance = self.ora().map(|ora| ora.target_name().to_string()).unwrap_or_default();
+
+        format!(
+            "asmfs {}\noracle {}.{} ({})\ninstance {}\n",
+            env!("CARGO_PKG_VERSION"),
+            self.oracle_version / 1000,
+            self.oracle_version % 1000,
+            self.oracle_version,
+            instance
+        )
+    }
+
+    pub(crate) fn render_handles(&self) -> String {
+        let mut out = String::new();
+
+        let _ = writeln!(out, "{:<6} {:>20} {:>6} {:>12} {:<14} {:>16} {:<7} {:>6}", "mode", "fh", "group", "file_number", "type", "bytes", "striped", "disks");
+
+        let mut raw: Vec<_> = self.handles_raw
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|(_, h)| self.shows_diskgroup(h.group_number))
+            .map(|(fh, h)| (*fh, h.group_number, h.file_number, h.file_type.clone(), h.file_size_bytes, h.striped, h.disk_list.len()))
+            .collect();
+        raw.sort_by_key(|h| h.0);
+
+        for (fh, group_number, file_number, file_type, bytes, striped, disks) in raw {
+            let striped = if striped == crate::oracle::ASM_STRIPED_FINE { "FINE" } else { "COARSE" };
+            let _ = writeln!(out, "{:<6} {:>20} {:>6} {:>12} {:<14} {:>16} {:<7} {:>6}", "raw", fh, group_number, file_number, file_type, bytes, striped, disks);
+        }
+
+        // each handle is locked after the map, it may be failing over to the next instance
+        let dbms: Vec<_> = self.handles_dbms
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(fh, h)| (*fh, Arc::clone(h)))
+            .collect();
+        let mut dbms: Vec<_> = dbms
+            .into_iter()
+            .map(|(fh, h)| {
+                let h = h.lock().unwrap();
+                (fh, Inode::from_ino(h.ino), h.file_type, h.bytes_size_fs())
+            })
+            .filter(|(_, inode, _, _)| self.shows_diskgroup(inode.get_group_number()))
+            .collect();
+        dbms.sort_by_key(|h| h.0);
+
+        // dbms_diskgroup.open() does not report a file number; show the inode's group instead
+        for (fh, inode, file_type, bytes) in dbms {
+            let _ = writeln!(out, "{:<6} {:>20} {:>6} {:>12} {:<14} {:>16} {:<7} {:>6}", "dbms", fh, inode.get_group_number(), "-", file_type, bytes, "-", "-");
+        }
+
+        out
+    }
+
+    fn render_diskgroups(&self) -> Result<String, Error> {
+        let ora = self.ora()?;
+        let diskgroups = ora.query_asm_diskgroup_details()?;
+        let mut out = String::new();
+
+        let _ = writeln!(out, "fine_stripe_width {}", self.fine_stripe_width);
+
+        for dg in diskgroups.into_iter().filter(|dg| self.shows_diskgroup(dg.group_number)) {
+            let _ = writeln!(out);
+            let _ = writeln!(out, "{} group_number={} au_size={} sector_size={} redundancy={} state={}",
+                             dg.name, dg.group_number, dg.au_size, dg.sector_size, dg.redundancy, dg.state);
+
+            let mut disks: Vec<(u16, String)> = ora.query_asm_disks(dg.group_number)?.into_iter().collect();
+            disks.sort();
+
+            for (disk_number, path) in disks {
+                let _ = writeln!(out, "  disk {:>4} {}", disk_number, path);
+            }
+        }
+
+        Ok(out)
+    }
+}
@@ -0,0 +1,194 @@
+use std::env;
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::os::unix::ffi::{OsStrExt, OsStringExt};
+use std::os::unix::fs::PermissionsExt;
+
+/*
+ * Where the password of a remote connection comes from, instead of `--conn user/pass@host`:
+ *
+ *   --conn-file PATH (-o conn_file=)   the connection string in a file only its owner can read (0600)
+ *   --conn user@host + ASMFS_PASSWORD  the password in the environment
+ *   --conn /@alias                     Oracle Wallet (secure external password store), found
+ *                                      through sqlnet.ora in TNS_ADMIN; no password at all
+ *
+ * A password that is still given on the command line is overwritten in /proc/self/cmdline (what
+ * ps shows) once it has been read, and is handed to the re-executed process (oraenv.rs) through
+ * ASMFS_PASSWORD rather than its arguments.
+ */
+
+pub const PASSWORD_ENV: &str = "ASMFS_PASSWORD";
+
+pub struct Credentials {
+    pub connection_string: Option<String>, // user/pass@host:port/service, /@alias or None for a local bequeath connection
+    pub command_line_password: Option<String>,
+}
+
+pub fn resolve(conn: Option<String>, conn_file: Option<String>) -> Result<Credentials, String> {
+    let (conn, command_line_password) = match (conn, conn_file) {
+        (Some(_), Some(_)) => return Err("--conn and --conn-file are mutually exclusive".to_string()),
+        (None, None) => return Ok(Credentials { connection_string: None, command_line_password: None }),
+        (None, Some(path)) => (read_conn_file(&path)?, None),
+        (Some(conn), None) => {
+            let password = split_connection_string(&conn)
+                .and_then(|(_, password, _)| password)
+                .filter(|password| !password.is_empty())
+                .map(str::to_string);
+            (conn, password)
+        }
+    };
+
+    let Some((user, password, target)) = split_connection_string(&conn) else {
+        return Err("Invalid connection string: expected user/pass@host:port/service, user@host:port/service or /@alias".to_string());
+    };
+
+    // a wallet entry, or a password given one way or another
+    if user.is_empty() || password.is_some_and(|password| !password.is_empty()) {
+        return Ok(Credentials { connection_string: Some(conn.clone()), command_line_password });
+    }
+
+    match env::var(PASSWORD_ENV) {
+        Ok(password) if !password.is_empty() => Ok(Credentials {
+            connection_string: Some(format!("{user}/{password}@{target}")),
+            command_line_password: None,
+        }),
+        _ => Err(format!("No password for {user}@{target}: set {PASSWORD_ENV}, use --conn-file or an Oracle Wallet (--conn /@alias)")),
+    }
+}
+
+// user/pass@target, as opposed to user@target and /@alias
+pub fn has_password(conn: &str) -> bool {
+    split_connection_string(conn)
+        .and_then(|(_, password, _)| password)
+        .is_some_and(|password| !password.is_empty())
+}
+
+// user/pass@target => (user, Some(pass), target); user@target => (user, None, target)
+fn split_connection_string(conn: &str) -> Option<(&str, Option<&str>, &str)> {
+    let (user_pass, target) = conn.split_once('@')?;
+
+    match user_pass.split_once('/') {
+        Some((user, password)) => Some((user, Some(password), target)),
+        None => Some((user_pass, None, target)),
+    }
+}
+
+fn read_conn_file(path: &str) -> Result<String, String> {
+    let metadata = fs::metadata(path).map_err(|e| format!("Unable to read conn file {path}: {e}"))?;
+
+    // like ssh with private keys: a password others can read is not secret anymore
+    if metadata.permissions().mode() & 0o077 != 0 {
+        return Err(format!("Conn file {path} is accessible by group or others, chmod 600 it"));
+    }
+
+    let content = fs::read_to_string(path).map_err(|e| format!("Unable to read conn file {path}: {e}"))?;
+
+    content
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .ok_or_else(|| format!("Conn file {path} contains no connection string"))
+}
+
+// `arg` with every "user/password@" turned into "user@", for the arguments of the re-exec
+pub fn without_password(arg: &OsStr, password: &str) -> OsString {
+    let needle = format!("/{password}@");
+    let bytes = arg.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut rest = bytes;
+
+    while let Some(index) = find(rest, needle.as_bytes()) {
+        out.extend_from_slice(&rest[..index]);
+        out.push(b'@');
+        rest = &rest[index + needle.len()..];
+    }
+    out.extend_from_slice(rest);
+
+    OsString::from_vec(out)
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|window| window == needle)
+}
+
+/*
+ * Overwrites `password` with '*' in the original argument strings of this process, the memory
+ * /proc/self/cmdline is read from. The kernel publishes where they are as arg_start and arg_end
+ * in /proc/self/stat (fields 48 and 49).
+ */
+pub fn scrub_cmdline(password: &str) {
+    let Some((arg_start, arg_end)) = cmdline_range() else {
+        return;
+    };
+
+    if password.is_empty() || arg_end <= arg_start {
+        return;
+    }
+
+    // SAFETY: [arg_start, arg_end) holds the argument strings the kernel copied onto the initial
+    // stack of this process, and no other thread exists yet. They are still in use: std keeps
+    // argv and args_os() reads it again on every call, and glibc's program_invocation_name points
+    // into argv[0]. Only password bytes become '*', lengths and NUL terminators stay, so these
+    // still see valid strings, with the password masked; main() collected args_os() before.
+    let cmdline = unsafe { std::slice::from_raw_parts_mut(arg_start as *mut u8, arg_end - arg_start) };
+    let needle = password.as_bytes();
+    let mut offset = 0;
+
+    while let Some(index) = find(&cmdline[offset..], needle) {
+        let start = offset + index;
+        cmdline[start..start + needle.len()].fill(b'*');
+        offset = start + needle.len();
+    }
+}
+
+fn cmdline_range() -> Option<(usize, usize)> {
+    let stat = fs::read_to_string("/proc/self/stat").ok()?;
+
+    // the command name (field 2) may contain spaces; fields after it start with state (field 3)
+    let fields: Vec<&str> = stat.rsplit_once(')')?.1.split_whitespace().collect();
+
+    let arg_start = fields.get(48 - 3)?.parse().ok()?;
+    let arg_end = fields.get(49 - 3)?.parse().ok()?;
+    Some((arg_start, arg_end))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn connection_strings_are_split() {
+        assert_eq!(split_connection_string("asmsnmp/secret@db1:1521/+ASM"), Some(("asmsnmp", Some("secret"), "db1:1521/+ASM")));
+        assert_eq!(split_connection_string("asmsnmp@db1:1521/+ASM"), Some(("asmsnmp", None, "db1:1521/+ASM")));
+        assert_eq!(split_connection_string("/@asm_wallet"), Some(("", Some(""), "asm_wallet")));
+        assert_eq!(split_connection_string("asmsnmp/@db1"), Some(("asmsnmp", Some(""), "db1")));
+        assert_eq!(split_connection_string("asmsnmp/secret"), None);
+
+        // the first '@' ends the password, the first '/' the user
+        assert_eq!(split_connection_string("asmsnmp/se/cret@db1@x"), Some(("asmsnmp", Some("se/cret"), "db1@x")));
+    }
+
+    #[test]
+    fn only_user_slash_password_counts_as_a_password() {
+        assert!(has_password("asmsnmp/secret@db1"));
+        assert!(!has_password("asmsnmp@db1"));
+        assert!(!has_password("asmsnmp/@db1"));
+        assert!(!has_password("/@asm_wallet"));
+        assert!(!has_password("asmsnmp"));
+    }
+
+    #[test]
+    fn password_is_removed_from_connection_strings_only() {
+        let strip = |arg: &str| without_password(OsStr::new(arg), "tiger").into_string().unwrap();
+
+        assert_eq!(strip("scott/tiger@db1:1521/+ASM"), "scott@db1:1521/+ASM");
+        assert_eq!(strip("--conn=scott/tiger@tiger-host/tiger"), "--conn=scott@tiger-host/tiger");
+        assert_eq!(strip("conn=scott/tiger@db1,failover_conn=scott/tiger@db2"), "conn=scott@db1,failover_conn=scott@db2");
+
+        // the same text in another argument is no password there
+        assert_eq!(strip("/var/log/tiger.log"), "/var/log/tiger.log");
+        assert_eq!(strip("+DATA/tiger"), "+DATA/tiger");
+        assert_eq!(strip("scott/tigers@db1"), "scott/tigers@db1");
+    }
+}
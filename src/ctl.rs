@@ -0,0 +1,221 @@
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::Shutdown;
+use std::os::unix::fs::FileTypeExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::sync::Arc;
+use std::sync::atomic::Ordering;
+use std::thread;
+use log::{error, info, warn, LevelFilter};
+use crate::disks::is_disk_device;
+use crate::filter::PathFilter;
+use crate::fuse::{connect_metadata, AsmFS};
+use crate::logging;
+use crate::policy::AccessPolicy;
+
+/*
+ * Control socket (-o control_socket=PATH) and its client, `asmfs ctl PATH COMMAND [ARGS...]`,
+ * for changes that would otherwise need a remount. One command per connection: the client
+ * writes a line, the server answers with text and closes the connection. Failures are
+ * answered with a single `error: ...` line.
+ *
+ * The socket is created with mode 0600; whoever can use it can change what the mount exposes.
+ */
+
+const USAGE: &str = "\
+Usage: asmfs ctl <SOCKET> <COMMAND> [ARGS...]
+
+Commands:
+  handles                               list open raw and DBMS_DISKGROUP handles
+  flush                                 clear cached ASM user/group names, re-read the extent maps of open raw handles
+  log-level <off|error|warn|info|debug|trace|reset>
+                                        change the log level; reset goes back to RUST_LOG
+  throttle                              show the I/O limits
+  throttle <SCOPE> <BYTES/S> <IOPS>     set the limit of one scope, e.g. `throttle group=DATA 100M -`
+  reload                                re-read the include/exclude rules and the policy file
+  reconnect                             replace the metadata connection to ASM, trying --conn first
+";
+
+pub fn start_server(asmfs: Arc<AsmFS>, path: &str) -> Result<(), String> {
+    let listener = bind_private_socket(path, "control")?;
+
+    thread::Builder::new()
+        .name("asmfs-ctl".to_string())
+        .spawn(move || {
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(stream) => serve(&asmfs, stream),
+                    Err(e) => error!("control socket: {}", e),
+                }
+            }
+        })
+        .map_err(|e| format!("Unable to start control socket thread: {e}"))?;
+
+    Ok(())
+}
+
+/*
+ * Binds a Unix socket only its owner can use, for the control socket and the metrics endpoint.
+ * The socket is created under umask 0177, so it is never connectable by others, not even
+ * between bind() and a chmod(). A socket left behind by a previous mount is replaced; one that
+ * still accepts connections belongs to a live mount and is an error.
+ */
+pub(crate) fn bind_private_socket(path: &str, what: &str) -> Result<UnixListener, String> {
+    if fs::symlink_metadata(path).is_ok_and(|metadata| metadata.file_type().is_socket()) {
+        if UnixStream::connect(path).is_ok() {
+            return Err(format!("Unable to create {what} socket {path}: it is in use by another process"));
+        }
+        let _ = fs::remove_file(path);
+    }
+
+    // SAFETY: umask() cannot fail; a file another thread creates meanwhile is owner-only at worst
+    let previous_umask = unsafe { libc::umask(0o177) };
+    let listener = UnixListener::bind(path);
+    // SAFETY: as above
+    unsafe { libc::umask(previous_umask) };
+
+    listener.map_err(|e| format!("Unable to create {what} socket {path}: {e}"))
+}
+
+fn serve(asmfs: &AsmFS, stream: UnixStream) {
+    let mut line = String::new();
+
+    if let Err(e) = BufReader::new(&stream).take(4096).read_line(&mut line) {
+        warn!("control socket: unable to read command: {}", e);
+        return;
+    }
+
+    info!("control socket: {}", line.trim());
+
+    let answer = match asmfs.control_command(line.trim()) {
+        Ok(answer) => answer,
+        Err(e) => format!("error: {}\n", e),
+    };
+
+    if let Err(e) = (&stream).write_all(answer.as_bytes()) {
+        warn!("control socket: unable to answer: {}", e);
+    }
+}
+
+// `asmfs ctl ...`; returns the exit code
+pub fn run_client(args: &[String]) -> i32 {
+    let [socket, command @ ..] = args else {
+        eprint!("{}", USAGE);
+        return 2;
+    };
+
+    if command.is_empty() || socket == "-h" || socket == "--help" {
+        eprint!("{}", USAGE);
+        return 2;
+    }
+
+    let answer = UnixStream::connect(socket).and_then(|mut stream| {
+        writeln!(stream, "{}", command.join(" "))?;
+        stream.shutdown(Shutdown::Write)?;
+
+        let mut answer = String::new();
+        stream.read_to_string(&mut answer)?;
+        Ok(answer)
+    });
+
+    match answer {
+        Ok(answer) if answer.starts_with("error: ") => {
+            eprint!("{}", answer);
+            1
+        }
+        Ok(answer) => {
+            print!("{}", answer);
+            0
+        }
+        Err(e) => {
+            eprintln!("Unable to talk to {socket}: {e}");
+            1
+        }
+    }
+}
+
+impl AsmFS {
+    fn control_command(&self, line: &str) -> Result<String, String> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+
+        match words[..] {
+            ["handles"] => Ok(self.render_handles()),
+            ["flush"] => Ok(self.flush_caches()),
+            ["log-level", "reset"] => {
+                logging::set_level(None);
+                Ok("log level reset to RUST_LOG\n".to_string())
+            }
+            ["log-level", level] => {
+                let level: LevelFilter = level.parse().map_err(|_| format!("invalid log level '{level}'"))?;
+                logging::set_level(Some(level));
+                Ok(format!("log level set to {}\n", level))
+            }
+            ["throttle"] => Ok(self.throttle.render()),
+            ["throttle", scope, bytes_per_sec, ops_per_sec] => {
+                self.throttle.set(&format!("{scope} {bytes_per_sec} {ops_per_sec}"))?;
+                Ok(self.throttle.render())
+            }
+            ["reload"] => self.reload_rules(),
+            ["reconnect"] => {
+                // the first instance that answers, i.e. back to --conn after a failover
+                let ora = connect_metadata(&self.config, 0)?;
+                let target = ora.target_name().to_string();
+                *self.ora.lock().unwrap() = ora;
+                self.stats.reconnects.fetch_add(1, Ordering::Relaxed);
+                Ok(format!("reconnected to {}\n", target))
+            }
+            [] => Err("empty command".to_string()),
+            _ => Err(format!("unknown command '{line}', see `asmfs ctl --help`")),
+        }
+    }
+
+    /*
+     * ASM user and usergroup names are looked up again on next use. Open raw handles get a fresh
+     * extent map and disk list, e.g. after a rebalance moved their extents; a handle that cannot
+     * be re-opened keeps the old one. Reads in flight finish on the handle they started with.
+     */
+    fn flush_caches(&self) -> String {
+        self.id_cache.clear();
+
+        let handles: Vec<(u64, u64)> = self.handles_raw
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(fh, handle)| (*fh, handle.ino))
+            .collect();
+
+        let mut reopened = 0;
+
+        for (fh, ino) in &handles {
+            let handle = if is_disk_device(*ino) {
+                self.open_disk_device(*ino)
+            } else {
+                self.ora().unwrap().proc_open_raw(*ino, self.config.mirror, self.config.hybrid)
+            };
+
+            match handle {
+                Ok(handle) => {
+                    // unless it was released in the meantime
+                    if let Some(slot) = self.handles_raw.write().unwrap().get_mut(fh) {
+                        *slot = Arc::new(handle);
+                        reopened += 1;
+                    }
+                }
+                Err(e) => warn!("flush: fh {} keeps its extent map: {}", fh, e),
+            }
+        }
+
+        format!("ASM user/group names flushed, extent maps of {} of {} raw handles re-read\n", reopened, handles.len())
+    }
+
+    // both files are parsed before either is replaced, so a broken file changes nothing
+    fn reload_rules(&self) -> Result<String, String> {
+        let filter = PathFilter::load(&self.config)?;
+        let policy = self.config.policy_file.as_deref().map(AccessPolicy::load).transpose()?;
+
+        *self.filter.write().unwrap() = filter;
+        *self.policy.write().unwrap() = policy;
+
+        Ok("include/exclude rules and policy reloaded\n".to_string())
+    }
+}
@@ -0,0 +1,53 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io;
+use std::sync::RwLock;
+use log::warn;
+use crate::fuse::AsmFS;
+use crate::inode::Inode;
+
+/*
+ * The ASM disks of the mount, opened once while asmfs still runs as root, before -o run_as= (see
+ * privileges.rs) gives root up: AFD lets only root open its disks, but a descriptor that is
+ * already open keeps working. Raw handles get a duplicate of the pooled descriptor. A disk that
+ * is not in the pool, e.g. one added to a diskgroup later, is opened as before, with whatever
+ * privileges are left by then.
+ */
+
+static POOL: RwLock<BTreeMap<String, File>> = RwLock::new(BTreeMap::new()); // device path => open disk
+
+// every File::open() of an ASM disk goes through here
+pub fn open(path: &str) -> io::Result<File> {
+    if let Some(file) = POOL.read().unwrap().get(path) {
+        return file.try_clone();
+    }
+
+    File::open(path)
+}
+
+impl AsmFS {
+    // opens every disk of the diskgroups this mount shows; returns how many
+    pub(crate) fn open_device_pool(&self) -> Result<usize, String> {
+        let ora = self.ora().map_err(|e| format!("Unable to open disks: {e}"))?;
+        let diskgroups = ora.query_asm_diskgroup_vec().map_err(|e| format!("Unable to query diskgroups: {e}"))?;
+        let mut pool = POOL.write().unwrap();
+
+        for (group_ino, _, name) in diskgroups {
+            let group_number = Inode::from_ino(group_ino).get_group_number();
+            let disks = ora.query_asm_disks(group_number).map_err(|e| format!("Unable to query disks of {name}: {e}"))?;
+
+            for path in disks.into_values() {
+                match File::open(&path) {
+                    Ok(file) => {
+                        pool.insert(path, file);
+                    },
+                    // -o hybrid reads what is on this disk through dbms_diskgroup
+                    Err(e) if self.config.hybrid => warn!("Disk {path} of {name} is not accessible, read through dbms_diskgroup: {e}"),
+                    Err(e) => return Err(format!("Unable to open disk {path} of {name}: {e}")),
+                }
+            }
+        }
+
+        Ok(pool.len())
+    }
+}
@@ -0,0 +1,269 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::os::unix::fs::FileExt;
+use fuser::{FileAttr, FileType};
+use oracle::{Error, ErrorKind};
+use crate::control::{synthetic_attr_of, CONTROL_DIR_NAME};
+use crate::devices;
+use crate::fuse::AsmFS;
+use crate::inode::{Inode, SYNTHETIC_DISK, SYNTHETIC_DISKS, SYNTHETIC_DISK_DEVICE, SYNTHETIC_DISK_HEADER};
+use crate::oracle::{RawOpenFileHandle, ASM_STRIPED_COARSE};
+
+/*
+ * /.asmfs/disks/<group>/<disk_number>/ exposes each ASM disk for forensic work:
+ *
+ *   device       the whole block device, read-only, served through a coarse RawOpenFileHandle
+ *                whose extent map is simply every AU of the disk in order
+ *   header.txt   the disk header (kfdhdb) decoded, like `kfed read` would show it
+ */
+
+const DISK_HEADER_SIZE: usize = 512; // every kfdhdb field used here lives in the first sector
+
+fn disk_payload(group_number: u8, disk_number: u16) -> u32 {
+    ((group_number as u32) << 16) | disk_number as u32
+}
+
+fn split_disk_payload(payload: u32) -> (u8, u16) {
+    ((payload >> 16) as u8, payload as u16)
+}
+
+pub fn is_disk_device(ino: u64) -> bool {
+    let inode = Inode::from_ino(ino);
+    inode.is_synthetic() && inode.get_synthetic_kind() == SYNTHETIC_DISK_DEVICE
+}
+
+// path of a disk device below the mount point, e.g. .asmfs/disks/1/0/device
+pub fn disk_device_path(ino: u64) -> String {
+    let (group_number, disk_number) = split_disk_payload(Inode::from_ino(ino).get_synthetic_payload());
+    format!("{}/disks/{}/{}/device", CONTROL_DIR_NAME, group_number, disk_number)
+}
+
+fn unknown_disk_inode(ino: u64) -> Error {
+    Error::new(ErrorKind::Other, format!("asmfs; unknown disk inode {}", ino))
+}
+
+fn hidden_diskgroup(group_number: u8) -> Error {
+    Error::new(ErrorKind::Other, format!("asmfs; diskgroup {} is outside of the root", group_number))
+}
+
+impl AsmFS {
+    pub(crate) fn disks_attr(&self, ino: u64) -> Result<FileAttr, Error> {
+        let inode = Inode::from_ino(ino);
+        let payload = inode.get_synthetic_payload();
+
+        match inode.get_synthetic_kind() {
+            SYNTHETIC_DISKS if payload == 0 => Ok(synthetic_attr_of(ino, FileType::Directory, 0)),
+            SYNTHETIC_DISKS if !self.shows_diskgroup(payload as u8) => Err(hidden_diskgroup(payload as u8)),
+            SYNTHETIC_DISKS => {
+                let ora = self.ora()?;
+                ora.query_asm_diskgroup_name(payload as u8)?;
+                ora.check_diskgroup_number(payload as u8)?;
+                Ok(synthetic_attr_of(ino, FileType::Directory, 0))
+            }
+            kind @ (SYNTHETIC_DISK | SYNTHETIC_DISK_DEVICE | SYNTHETIC_DISK_HEADER) => {
+                let (group_number, disk_number) = split_disk_payload(payload);
+                if !self.shows_diskgroup(group_number) {
+                    return Err(hidden_diskgroup(group_number));
+                }
+                let disk = self.ora()?.query_asm_disk(group_number, disk_number)?;
+
+                Ok(match kind {
+                    SYNTHETIC_DISK => synthetic_attr_of(ino, FileType::Directory, 0),
+                    SYNTHETIC_DISK_DEVICE => synthetic_attr_of(ino, FileType::RegularFile, disk.os_mb * 1024 * 1024),
+                    _ => synthetic_attr_of(ino, FileType::RegularFile, 0),
+                })
+            }
+            _ => Err(unknown_disk_inode(ino)),
+        }
+    }
+
+    pub(crate) fn disks_parent_ino(&self, ino: u64) -> u64 {
+        let inode = Inode::from_ino(ino);
+        let payload = inode.get_synthetic_payload();
+
+        match inode.get_synthetic_kind() {
+            SYNTHETIC_DISKS => Inode::from_synthetic(SYNTHETIC_DISKS, 0).get_ino(),
+            SYNTHETIC_DISK => Inode::from_synthetic(SYNTHETIC_DISKS, split_disk_payload(payload).0 as u32).get_ino(),
+            _ => Inode::from_synthetic(SYNTHETIC_DISK, payload).get_ino(),
+        }
+    }
+
+    pub(crate) fn disks_children(&self, ino: u64) -> Result<Vec<(u64, FileType, String)>, Error> {
+        let inode = Inode::from_ino(ino);
+        let payload = inode.get_synthetic_payload();
+
+        match inode.get_synthetic_kind() {
+            SYNTHETIC_DISKS if payload == 0 => {
+                let diskgroups = self.ora()?.query_asm_diskgroup_vec()?;
+                Ok(diskgroups
+                    .into_iter()
+                    .map(|(group_ino, kind, name)| (Inode::from_ino(group_ino).get_group_number(), kind, name))
+                    .filter(|(group_number, _, _)| self.shows_diskgroup(*group_number))
+                    .map(|(group_number, kind, name)| (Inode::from_synthetic(SYNTHETIC_DISKS, group_number as u32).get_ino(), kind, name))
+                    .collect())
+            }
+            SYNTHETIC_DISKS if !self.shows_diskgroup(payload as u8) => Err(hidden_diskgroup(payload as u8)),
+            SYNTHETIC_DISKS => {
+                let group_number = payload as u8;
+                let disks = self.ora()?.query_asm_disk_details(group_number)?;
+                Ok(disks
+                    .into_iter()
+                    .map(|disk| (Inode::from_synthetic(SYNTHETIC_DISK, disk_payload(group_number, disk.disk_number)).get_ino(), FileType::Directory, disk.disk_number.to_string()))
+                    .collect())
+            }
+            SYNTHETIC_DISK if !self.shows_diskgroup(split_disk_payload(payload).0) => Err(hidden_diskgroup(split_disk_payload(payload).0)),
+            SYNTHETIC_DISK => Ok(vec![
+                (Inode::from_synthetic(SYNTHETIC_DISK_DEVICE, payload).get_ino(), FileType::RegularFile, "device".to_string()),
+                (Inode::from_synthetic(SYNTHETIC_DISK_HEADER, payload).get_ino(), FileType::RegularFile, "header.txt".to_string()),
+            ]),
+            _ => Err(unknown_disk_inode(ino)),
+        }
+    }
+
+    pub(crate) fn open_disk_device(&self, ino: u64) -> Result<RawOpenFileHandle, Error> {
+        let (group_number, disk_number) = split_disk_payload(Inode::from_ino(ino).get_synthetic_payload());
+        if !self.shows_diskgroup(group_number) {
+            return Err(hidden_diskgroup(group_number));
+        }
+
+        let (disk, au_size) = {
+            let ora = self.ora()?;
+            (ora.query_asm_disk(group_number, disk_number)?, ora.query_au_size(group_number)?)
+        };
+
+        let file = devices::open(&disk.path)
+            .map_err(|e| Error::new(ErrorKind::Other, format!("Could not open block device {}: {}", disk.path, e)))?;
+
+        let file_size_bytes = disk.os_mb * 1024 * 1024;
+        let au_count = file_size_bytes.div_ceil(au_size as u64) as u32;
+
+        Ok(RawOpenFileHandle {
+            ino,
+            au_list: (0..au_count).map(|au| (disk_number, au)).collect(),
+            au_size,
+            file_size_bytes,
+            file_type: "DISK".to_string(),
+            disk_list: HashMap::from([(disk_number, file)]),
+            file_number: 0,
+            group_number,
+            striped: ASM_STRIPED_COARSE,
+            fine_stripe_count: 0,
+        })
+    }
+
+    pub(crate) fn render_disk_header(&self, ino: u64) -> Result<String, Error> {
+        let (group_number, disk_number) = split_disk_payload(Inode::from_ino(ino).get_synthetic_payload());
+        if !self.shows_diskgroup(group_number) {
+            return Err(hidden_diskgroup(group_number));
+        }
+        let disk = self.ora()?.query_asm_disk(group_number, disk_number)?;
+
+        let mut block = vec![0u8; DISK_HEADER_SIZE];
+        devices::open(&disk.path)
+            .and_then(|file| file.read_exact_at(&mut block, 0))
+            .map_err(|e| Error::new(ErrorKind::Other, format!("Could not read disk header of {}: {}", disk.path, e)))?;
+
+        let mut out = String::new();
+        let _ = writeln!(out, "path:             {}", disk.path);
+        let _ = writeln!(out, "v$asm_disk.name:  {}", disk.name);
+        let _ = writeln!(out, "failgroup:        {}", disk.failgroup);
+        let _ = writeln!(out);
+        out.push_str(&render_kfdhdb(&block));
+        Ok(out)
+    }
+}
+
+/*
+ * Disk header layout (kfbh block header followed by kfdhdb), offsets within block 0 of AU 0:
+ *
+ *   0x000 kfbh.endian (1 = little endian)    0x0a8 kfdhdb.capname [32]
+ *   0x002 kfbh.type (1 = KFBTYP_DISKHEAD)    0x0c8 kfdhdb.crestmp (hi, lo)
+ *   0x020 kfdhdb.driver.provstr [8]          0x0d0 kfdhdb.mntstmp (hi, lo)
+ *   0x040 kfdhdb.compat                      0x0d8 kfdhdb.secsize (u16)
+ *   0x044 kfdhdb.dsknum (u16)                0x0da kfdhdb.blksize (u16)
+ *   0x046 kfdhdb.grptyp, 0x047 hdrsts        0x0dc kfdhdb.ausize
+ *   0x048 kfdhdb.dskname [32]                0x0e4 kfdhdb.dsksize (in AUs)
+ *   0x068 kfdhdb.grpname [32]
+ *   0x088 kfdhdb.fgname [32]
+ */
+fn render_kfdhdb(block: &[u8]) -> String {
+    let little_endian = block[0x000] == 1;
+    let u16_at = |offset: usize| {
+        let bytes = [block[offset], block[offset + 1]];
+        if little_endian { u16::from_le_bytes(bytes) } else { u16::from_be_bytes(bytes) }
+    };
+    let u32_at = |offset: usize| {
+        let bytes: [u8; 4] = block[offset..offset + 4].try_into().unwrap();
+        if little_endian { u32::from_le_bytes(bytes) } else { u32::from_be_bytes(bytes) }
+    };
+    let str_at = |offset: usize, len: usize| {
+        let raw = &block[offset..offset + len];
+        let end = raw.iter().position(|b| *b == 0).unwrap_or(len);
+        String::from_utf8_lossy(&raw[..end]).trim_end().to_string()
+    };
+
+    let mut out = String::new();
+
+    if block[0x002] != 1 {
+        let _ = writeln!(out, "kfbh.type:        {} (not KFBTYP_DISKHEAD, this is not an ASM disk header)", block[0x002]);
+        return out;
+    }
+
+    let compat = u32_at(0x040);
+    let grptyp = match block[0x046] {
+        1 => "EXTERNAL",
+        2 => "NORMAL",
+        3 => "HIGH",
+        4 => "FLEX",
+        5 => "EXTENDED",
+        _ => "UNKNOWN",
+    };
+    let hdrsts = match block[0x047] {
+        0 => "INVALID",
+        1 => "UNKNOWN",
+        2 => "CANDIDATE",
+        3 => "MEMBER",
+        4 => "FORMER",
+        5 => "CONFLICT",
+        6 => "INCOMPAT",
+        7 => "PROVISIONED",
+        _ => "?",
+    };
+
+    let _ = writeln!(out, "provstr:          {}", str_at(0x020, 8));
+    let _ = writeln!(out, "compat:           {} (0x{:08x})", render_compat(compat), compat);
+    let _ = writeln!(out, "dsknum:           {}", u16_at(0x044));
+    let _ = writeln!(out, "grptyp:           {}", grptyp);
+    let _ = writeln!(out, "hdrsts:           {}", hdrsts);
+    let _ = writeln!(out, "dskname:          {}", str_at(0x048, 32));
+    let _ = writeln!(out, "grpname:          {}", str_at(0x068, 32));
+    let _ = writeln!(out, "fgname:           {}", str_at(0x088, 32));
+    let _ = writeln!(out, "capname:          {}", str_at(0x0a8, 32));
+    let _ = writeln!(out, "crestmp:          {}", render_kfdhdb_timestamp(u32_at(0x0c8), u32_at(0x0cc)));
+    let _ = writeln!(out, "mntstmp:          {}", render_kfdhdb_timestamp(u32_at(0x0d0), u32_at(0x0d4)));
+    let _ = writeln!(out, "secsize:          {}", u16_at(0x0d8));
+    let _ = writeln!(out, "blksize:          {}", u16_at(0x0da));
+    let _ = writeln!(out, "ausize:           {}", u32_at(0x0dc));
+    let _ = writeln!(out, "dsksize:          {} AUs", u32_at(0x0e4));
+
+    out
+}
+
+// 0x13000000 => 19.0.0.0.0
+fn render_compat(compat: u32) -> String {
+    format!("{}.{}.{}.{}.{}", compat >> 24, (compat >> 20) & 0xF, (compat >> 16) & 0xF, (compat >> 12) & 0xF, (compat >> 8) & 0xF)
+}
+
+// hi: YEAR << 14 | MNTH << 10 | DAYS << 5 | HOUR,  lo: MINS << 26 | SECS << 20 | MSEC << 10 | USEC
+fn render_kfdhdb_timestamp(hi: u32, lo: u32) -> String {
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}.{:03}",
+        hi >> 14,
+        (hi >> 10) & 0xF,
+        (hi >> 5) & 0x1F,
+        hi & 0x1F,
+        lo >> 26,
+        (lo >> 20) & 0x3F,
+        (lo >> 10) & 0x3FF
+    )
+}
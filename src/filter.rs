@@ -0,0 +1,179 @@
+use std::fs;
+use fuser::FileType;
+use regex::{Regex, RegexBuilder};
+use crate::config::MountConfig;
+
+/*
+ * Include/exclude rules, matched against full ASM paths such as +FRA/ORCL/ARCHIVELOG/2024_01_01/thread_1_seq_5.300.1.
+ * A rule is a glob (`*` matches any run of characters including '/', `?` matches one character)
+ * or, with the prefix `re:`, a regular expression. Matching ignores case, like ASM does.
+ *
+ *   - an entry matching any exclude rule is hidden, directories included
+ *   - when include rules exist, files (and symlinks) must match one of them; directories are
+ *     always walkable, otherwise nothing below them could ever be included
+ *
+ * Rules come from `-o include=`/`-o exclude=` (repeatable) and from `-o filter_file=`, a text
+ * file with one `include <pattern>` or `exclude <pattern>` per line and `#` comments.
+ */
+
+#[derive(Clone, Copy, PartialEq)]
+enum RuleKind {
+    Include,
+    Exclude,
+}
+
+pub struct PathFilter {
+    rules: Vec<(RuleKind, Regex)>,
+}
+
+impl PathFilter {
+    pub fn load(config: &MountConfig) -> Result<PathFilter, String> {
+        let mut rules = Vec::new();
+
+        for pattern in &config.include {
+            rules.push(compile_rule(RuleKind::Include, pattern)?);
+        }
+
+        for pattern in &config.exclude {
+            rules.push(compile_rule(RuleKind::Exclude, pattern)?);
+        }
+
+        if let Some(path) = &config.filter_file {
+            let content = fs::read_to_string(path)
+                .map_err(|e| format!("Unable to read filter file {path}: {e}"))?;
+
+            for (line_number, line) in content.lines().enumerate() {
+                let line = line.trim();
+
+                if line.is_empty() || line.starts_with('#') {
+                    continue;
+                }
+
+                let (kind, pattern) = match line.split_once(char::is_whitespace) {
+                    Some(("include", pattern)) => (RuleKind::Include, pattern.trim()),
+                    Some(("exclude", pattern)) => (RuleKind::Exclude, pattern.trim()),
+                    _ => return Err(format!("{path}:{}: expected 'include <pattern>' or 'exclude <pattern>'", line_number + 1)),
+                };
+
+                rules.push(compile_rule(kind, pattern).map_err(|e| format!("{path}:{}: {e}", line_number + 1))?);
+            }
+        }
+
+        Ok(PathFilter { rules })
+    }
+
+    pub fn is_active(&self) -> bool {
+        !self.rules.is_empty()
+    }
+
+    pub fn is_visible(&self, path: &str, kind: FileType) -> bool {
+        if self.rules.iter().any(|(rule, regex)| *rule == RuleKind::Exclude && regex.is_match(path)) {
+            return false;
+        }
+
+        if kind == FileType::Directory {
+            return true;
+        }
+
+        let mut includes = self.rules.iter().filter(|(rule, _)| *rule == RuleKind::Include).peekable();
+        includes.peek().is_none() || includes.any(|(_, regex)| regex.is_match(path))
+    }
+}
+
+fn compile_rule(kind: RuleKind, pattern: &str) -> Result<(RuleKind, Regex), String> {
+    let expression = match pattern.strip_prefix("re:") {
+        Some(expression) => expression.to_string(),
+        None => glob_to_regex(pattern),
+    };
+
+    let regex = RegexBuilder::new(&expression)
+        .case_insensitive(true)
+        .build()
+        .map_err(|e| format!("invalid filter pattern '{pattern}': {e}"))?;
+
+    Ok((kind, regex))
+}
+
+// */ONLINELOG/* => ^.*/ONLINELOG/.*$
+fn glob_to_regex(glob: &str) -> String {
+    let mut expression = String::from("^");
+    let mut buffer = [0u8; 4];
+
+    for c in glob.chars() {
+        match c {
+            '*' => expression.push_str(".*"),
+            '?' => expression.push('.'),
+            c => expression.push_str(&regex::escape(c.encode_utf8(&mut buffer))),
+        }
+    }
+
+    expression.push('$');
+    expression
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(rules: &[(RuleKind, &str)]) -> PathFilter {
+        PathFilter { rules: rules.iter().map(|(kind, pattern)| compile_rule(*kind, pattern).unwrap()).collect() }
+    }
+
+    #[test]
+    fn glob_escapes_regex_characters() {
+        assert_eq!(glob_to_regex("*/ONLINELOG/*"), "^.*/ONLINELOG/.*$");
+        assert_eq!(glob_to_regex("+DATA/a.b$"), r"^\+DATA/a\.b\$$");
+
+        let rule = filter(&[(RuleKind::Include, "+FRA/*.dbf")]);
+        assert!(rule.is_visible("+FRA/ORCL/users.dbf", FileType::RegularFile));
+        assert!(!rule.is_visible("+FRA/ORCL/usersxdbf", FileType::RegularFile));
+        assert!(!rule.is_visible("FRA/ORCL/users.dbf", FileType::RegularFile));
+    }
+
+    #[test]
+    fn question_mark_matches_one_character() {
+        let rule = filter(&[(RuleKind::Include, "+FRA/group_?.log")]);
+        assert!(rule.is_visible("+FRA/group_1.log", FileType::RegularFile));
+        assert!(!rule.is_visible("+FRA/group_12.log", FileType::RegularFile));
+        assert!(!rule.is_visible("+FRA/group_.log", FileType::RegularFile));
+    }
+
+    #[test]
+    fn re_prefix_takes_a_regular_expression() {
+        let rule = filter(&[(RuleKind::Exclude, r"re:/thread_\d+_seq_")]);
+        assert!(!rule.is_visible("+FRA/ORCL/ARCHIVELOG/thread_1_seq_5.300.1", FileType::RegularFile));
+        assert!(rule.is_visible("+FRA/ORCL/ARCHIVELOG/thread_x_seq_5.300.1", FileType::RegularFile));
+
+        assert!(compile_rule(RuleKind::Exclude, "re:(").is_err());
+    }
+
+    #[test]
+    fn matching_ignores_case() {
+        let rule = filter(&[(RuleKind::Exclude, "*/onlinelog/*")]);
+        assert!(!rule.is_visible("+DATA/ORCL/ONLINELOG/group_1.262.1", FileType::RegularFile));
+    }
+
+    #[test]
+    fn exclude_beats_include() {
+        let rules = filter(&[(RuleKind::Include, "+FRA/*"), (RuleKind::Exclude, "*/ONLINELOG/*")]);
+        assert!(rules.is_visible("+FRA/ORCL/ARCHIVELOG/arch_1.log", FileType::RegularFile));
+        assert!(!rules.is_visible("+FRA/ORCL/ONLINELOG/group_1.262.1", FileType::RegularFile));
+        assert!(!rules.is_visible("+FRA/ORCL/ONLINELOG/old", FileType::Directory));
+    }
+
+    #[test]
+    fn directories_stay_visible_under_include_rules() {
+        let rules = filter(&[(RuleKind::Include, "*/ARCHIVELOG/*")]);
+        assert!(rules.is_visible("+FRA/ORCL", FileType::Directory));
+        assert!(rules.is_visible("+FRA/ORCL/ARCHIVELOG/arch_1.log", FileType::RegularFile));
+        assert!(!rules.is_visible("+FRA/ORCL/spfile.ora", FileType::RegularFile));
+        assert!(!rules.is_visible("+FRA/ORCL/spfile.ora", FileType::Symlink));
+    }
+
+    #[test]
+    fn no_rules_show_everything() {
+        let rules = filter(&[]);
+        assert!(!rules.is_active());
+        assert!(rules.is_visible("+DATA/ORCL/spfile.ora", FileType::RegularFile));
+    }
+}
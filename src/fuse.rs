@@ -1,24 +1,37 @@
-use fuser::{Errno, FileAttr, FileHandle, FileType, Filesystem, FopenFlags, Generation, INodeNo, InitFlags, KernelConfig, LockOwner, OpenFlags, ReplyAttr, ReplyData, ReplyDirectory, ReplyEmpty, ReplyEntry, ReplyOpen, ReplyXattr, Request};
+use fuser::{Errno, FileAttr, FileHandle, FileType, Filesystem, FopenFlags, Generation, INodeNo, InitFlags, KernelConfig, LockOwner, OpenFlags, ReplyAttr, ReplyData, ReplyDirectory, ReplyEmpty, ReplyEntry, ReplyOpen, ReplyStatfs, ReplyXattr, Request};
 use std::ffi::OsStr;
 use std::collections::HashMap;
-use std::time::{Duration, UNIX_EPOCH};
+use std::time::{Duration, Instant, UNIX_EPOCH};
 use std::os::unix::fs::FileExt;
-use std::sync::{Arc, Mutex, RwLock};
-use log::{debug, info, error}; // debug
+use std::sync::{Arc, LockResult, Mutex, MutexGuard, RwLock};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use log::{debug, info, error, warn}; // debug
 use crate::oracle::{OracleConnection, RawOpenFileHandle, fix_header_block, ASM_STRIPED_COARSE, ASM_STRIPED_FINE, MAGIC_FILE_TYPES, synthetic_dir_time};
-use oracle::{Error};
-use crate::inode::Inode;
+use oracle::{Error, ErrorKind};
+use crate::inode::{Inode, ENTRY_VIEW_BY_NUMBER, VIEW_DIR};
+use crate::audit::AuditLog;
+use crate::config::MountConfig;
+use crate::control::{CONTROL_DIR_NAME, control_dir_ino, is_control_file};
+use crate::disks::is_disk_device;
+use crate::filter::PathFilter;
+use crate::permissions::IdCache;
+use crate::policy::{AccessPolicy, Requester};
+use crate::stats::{Op, Stats};
+use crate::throttle::Throttle;
+use crate::views::{view_entries, view_lookup};
 
 
 const TTL: Duration = Duration::from_secs(60);  // 1 minute
 const TTL_DIR: Duration = Duration::from_secs(10); // 10 seconds
 
-struct OpenFileHandle {
+pub(crate) struct OpenFileHandle {
     conn: OracleConnection,
+    handle: u64,           // of dbms_diskgroup.open(), the fh until a failover opens the file again
+    pub(crate) ino: u64,
     block_size: u32,
     blocks_asm: u64,
     blocks_fs: u64,
-    file_type: u32
+    pub(crate) file_type: u32
 }
 
 impl OpenFileHandle {
@@ -31,29 +44,51 @@ impl OpenFileHandle {
 }
 
 pub struct AsmFS {
-    ora: Mutex<OracleConnection>,
-    connection_string: Option<String>,      // read-only after init
-    mount_point: String,                    // read-only after init
-    handles_dbms: Mutex<HashMap<u64, OpenFileHandle>>,
-    handles_raw: RwLock<HashMap<u64, Arc<RawOpenFileHandle>>>,
-    use_raw: bool,  // read only after init
-    mirror: u8,     // read only after init
-    magic: bool,    // read only after init
-    owner_uid: u32, // read only after init
-    owner_gid: u32, // read only after init
-    oracle_version: u32, // only written in constructor
-    fine_stripe_width: u32  // only written in constructor
+    pub(crate) ora: Mutex<OracleConnection>,
+    reconnecting: AtomicBool,                          // a thread in ora() is connecting to the next instance
+    pub(crate) config: MountConfig,                    // read-only after init
+    pub(crate) handles_dbms: Mutex<HashMap<u64, Arc<Mutex<OpenFileHandle>>>>, // locked per file, a read may fail over
+    pub(crate) handles_raw: RwLock<HashMap<u64, Arc<RawOpenFileHandle>>>,
+    pub(crate) handles_gap: Mutex<HashMap<u64, Arc<Mutex<Option<OpenFileHandle>>>>>, // -o hybrid: raw fh => dbms_diskgroup handle of the same file, opened on first gap, locked per file
+    pub(crate) handles_control: Mutex<HashMap<u64, Vec<u8>>>, // fh => content snapshot of a /.asmfs/ file
+    next_fh: AtomicU64,                                // fh of raw and control handles, one per open()
+    pub(crate) stats: Stats,
+    pub(crate) filter: RwLock<PathFilter>,     // include/exclude rules, replaced by `asmfs ctl reload`
+    pub(crate) id_cache: IdCache,   // -o asm_acl: ASM user and usergroup names => local uid/gid
+    pub(crate) policy: RwLock<Option<AccessPolicy>>, // -o policy_file=, replaced by `asmfs ctl reload`
+    audit: Option<AuditLog>,        // -o audit_log=
+    pub(crate) throttle: Throttle,  // limits on raw reads
+    root_ino: Option<u64>,          // ASM directory shown as inode 1 (-o root=), only written in constructor
+    pub(crate) oracle_version: u32, // only written in constructor
+    pub(crate) fine_stripe_width: u32  // only written in constructor
 }
 
 impl AsmFS {
-    pub fn new(mut mount_point: String, connection_string: Option<String>, use_raw: bool, magic: bool, mirror: u8, owner_uid: u32, owner_gid: u32) -> Result<Self, String> {
-        if !mount_point.ends_with("/") {
-            mount_point.push('/');
+    pub fn new(mut config: MountConfig) -> Result<Self, String> {
+        if !config.mount_point.ends_with("/") {
+            config.mount_point.push('/');
         }
 
+        let filter = PathFilter::load(&config)?;
+        let policy = config.policy_file.as_deref().map(AccessPolicy::load).transpose()?;
+        let audit = config.audit_log.as_deref().map(AuditLog::open).transpose()?;
+
         info!("Connecting to oracle...");
-        let ora = OracleConnection::connect(connection_string.clone())
-            .map_err(|e| format!("Unable to connect to oracle: {e}"))?;
+        let ora = connect_metadata(&config, 0)?;
+        info!("Connected to {}", ora.target_name());
+
+        let groups = ora
+            .query_asm_diskgroup_vec()
+            .map_err(|e| format!("Unable to query diskgroups: {e}"))?
+            .into_iter()
+            .map(|(ino, _, name)| (name.trim_start_matches('+').to_uppercase(), Inode::from_ino(ino).get_group_number()))
+            .collect();
+        let throttle = Throttle::load(&config, groups)?;
+
+        let root_ino = match &config.root {
+            Some(root) => Some(resolve_root(&ora, root).map_err(|e| format!("Unable to resolve root {root}: {e}"))?),
+            None => None,
+        };
 
         let oracle_version = ora
             .query_oracle_version()
@@ -65,44 +100,361 @@ impl AsmFS {
 
         Ok(AsmFS {
             ora: Mutex::new(ora),
-            connection_string,
-            mount_point,
+            reconnecting: AtomicBool::new(false),
+            config,
             handles_dbms: Mutex::new(HashMap::new()),
             handles_raw: RwLock::new(HashMap::new()),
-            use_raw,
-            mirror,
-            magic,
-            owner_uid,
-            owner_gid,
+            handles_gap: Mutex::new(HashMap::new()),
+            handles_control: Mutex::new(HashMap::new()),
+            next_fh: AtomicU64::new(1),
+            stats: Stats::new(),
+            filter: RwLock::new(filter),
+            id_cache: IdCache::default(),
+            policy: RwLock::new(policy),
+            audit,
+            throttle,
+            root_ino,
             oracle_version,
             fine_stripe_width })
     }
 
-    fn with_configured_owner(&self, mut attr: FileAttr) -> FileAttr {
-        attr.uid = self.owner_uid;
-        attr.gid = self.owner_gid;
-        attr
+    // names of views, file types and databases are matched here rather than in SQL
+    pub(crate) fn name_matches(&self, canonical: &str, name: &str) -> bool {
+        if self.config.ignore_case { canonical.eq_ignore_ascii_case(name) } else { canonical == name }
+    }
+
+    /*
+     * With -o root=, the kernel's inode 1 stands for an ASM directory, which keeps its own inode
+     * number everywhere else. Inode numbers coming from the kernel pass through real_ino(), and
+     * the ones handed back to it through kernel_ino().
+     */
+    fn real_ino(&self, ino: INodeNo) -> INodeNo {
+        match self.root_ino {
+            Some(root_ino) if ino.0 == 1 => INodeNo(root_ino),
+            _ => ino,
+        }
+    }
+
+    fn kernel_ino(&self, ino: u64) -> u64 {
+        if Some(ino) == self.root_ino { 1 } else { ino }
+    }
+
+    fn is_root(&self, ino: u64) -> bool {
+        ino == self.root_ino.unwrap_or(1)
+    }
+
+    // with -o root=, /.asmfs shows only the diskgroup of the root, not the disks and files of others
+    pub(crate) fn shows_diskgroup(&self, group_number: u8) -> bool {
+        self.root_ino.is_none_or(|root_ino| Inode::from_ino(root_ino).get_group_number() == group_number)
+    }
+
+    /*
+     * -o diskgroups: an NFS client can present a file handle of any inode without a lookup, so
+     * the aliases and views of an excluded diskgroup are refused by inode too. The root and
+     * /.asmfs are not in a diskgroup; a diskgroup directory is checked by its own query.
+     */
+    fn check_diskgroup(&self, ino: u64) -> Result<(), Error> {
+        let inode = Inode::from_ino(ino);
+        if ino == 1 || inode.is_synthetic() || inode.is_disk_group() {
+            return Ok(());
+        }
+
+        self.ora()?.check_diskgroup_number(inode.get_group_number())
+    }
+
+    /*
+     * The metadata connection, for every query. Once a statement has found its session or
+     * instance gone (a RAC node evicted, an instance shut down), the connection is replaced here
+     * by one to the next instance of --conn and --failover-conn, before the next query. The
+     * statement that failed is not repeated; its request gets an error. Connecting can take the
+     * connect timeout of every instance, so it happens outside the lock; requests meanwhile get
+     * the lost connection and fail at once instead of waiting.
+     */
+    pub(crate) fn ora(&self) -> LockResult<MutexGuard<'_, OracleConnection>> {
+        let (target, target_name) = {
+            let ora = self.ora.lock()?;
+            if !ora.is_lost() {
+                return Ok(ora);
+            }
+            (ora.target(), ora.target_name().to_string())
+        };
+
+        // one thread connects, outside the lock; the others fail fast on the lost connection meanwhile
+        if self.reconnecting.swap(true, Ordering::AcqRel) {
+            return self.ora.lock();
+        }
+
+        let replacement = connect_metadata(&self.config, target + 1);
+        let ora = self.ora.lock();
+        self.reconnecting.store(false, Ordering::Release);
+        let mut ora = ora?;
+
+        match replacement {
+            Ok(replacement) if ora.is_lost() => {
+                info!("Failed over from {} to {}", target_name, replacement.target_name());
+                *ora = replacement;
+                self.stats.reconnects.fetch_add(1, Ordering::Relaxed);
+            }
+            Ok(_) => debug!("Failover from {} not needed anymore, replaced meanwhile", target_name),
+            Err(e) => error!("Failover from {} failed: {}", target_name, e),
+        }
+
+        Ok(ora)
+    }
+
+    /*
+     * ASM path the include/exclude rules are matched against. None for entries the rules never
+     * apply to: the root, /.asmfs/ and the view directories. Files listed inside the views are
+     * aliases and have a path like any other alias.
+     */
+    pub(crate) fn asm_path(&self, ino: u64) -> Result<Option<String>, Error> {
+        let inode = Inode::from_ino(ino);
+
+        if ino == 1 || inode.is_synthetic() {
+            return Ok(None);
+        }
+
+        if inode.is_view() {
+            // files that lost all aliases only exist in .by-number/ and go by their numeric name (+DATA.256.1)
+            if inode.get_view() == ENTRY_VIEW_BY_NUMBER && inode.get_alias_index() != VIEW_DIR {
+                return self.ora()?.query_file_path(ino).map(Some);
+            }
+            return Ok(None);
+        }
+
+        let ora = self.ora()?;
+
+        if inode.is_disk_group() {
+            ora.query_asm_diskgroup_name(inode.get_group_number()).map(Some)
+        } else {
+            ora.query_asm_alias_path(ino).map(Some)
+        }
+    }
+
+    // readdir knows the directory's path already, so aliases listed in it need no query of their own
+    fn child_path(&self, dir_path: Option<&str>, child_ino: u64, name: &str) -> Result<Option<String>, Error> {
+        let child = Inode::from_ino(child_ino);
+
+        match dir_path {
+            Some(dir_path) if !child.is_view() && !child.is_synthetic() => Ok(Some(format!("{}/{}", dir_path, name))),
+            _ => self.asm_path(child_ino),
+        }
+    }
+
+    // the path the access policy is checked against: view directories belong to their diskgroup
+    fn policy_path(&self, ino: u64) -> Result<Option<String>, Error> {
+        let inode = Inode::from_ino(ino);
+
+        if inode.is_synthetic() {
+            return Ok(Some(CONTROL_DIR_NAME.to_string()));
+        }
+
+        if inode.is_view() && (inode.get_view() != ENTRY_VIEW_BY_NUMBER || inode.get_alias_index() == VIEW_DIR) {
+            return self.ora()?.query_asm_diskgroup_name(inode.get_group_number()).map(Some);
+        }
+
+        self.asm_path(ino)
+    }
+
+    // Err(path) when the access policy denies the calling process this entry
+    fn policy_check(&self, req: &Request, path: Result<Option<String>, Error>, kind: FileType) -> Result<(), String> {
+        let policy = self.policy.read().unwrap();
+        let policy = match policy.as_ref() {
+            Some(policy) => policy,
+            None => return Ok(()),
+        };
+
+        let path = path.map_err(|e| format!("(unresolved: {})", e))?;
+        let requester = Requester { uid: req.uid(), gid: req.gid(), pid: req.pid() };
+
+        if policy.allows(&requester, path.as_deref(), kind) {
+            Ok(())
+        } else {
+            Err(path.unwrap_or_default())
+        }
+    }
+
+    fn policy_check_ino(&self, req: &Request, ino: u64, kind: FileType) -> Result<(), String> {
+        if !self.policy_active() {
+            return Ok(());
+        }
+
+        self.policy_check(req, self.policy_path(ino), kind)
+    }
+
+    fn log_denied(req: &Request, op: &str, path: &str) {
+        warn!("access denied by policy: op={} uid={} gid={} pid={} path={}", op, req.uid(), req.gid(), req.pid(), path);
+    }
+
+    fn filter_active(&self) -> bool {
+        self.filter.read().unwrap().is_active()
+    }
+
+    fn policy_active(&self) -> bool {
+        self.policy.read().unwrap().is_some()
+    }
+
+    // entries hidden by the include/exclude rules behave as if they did not exist
+    fn is_filtered(&self, path: Result<Option<String>, Error>, kind: FileType) -> bool {
+        match path {
+            Ok(Some(path)) => {
+                let filtered = !self.filter.read().unwrap().is_visible(&path, kind);
+                if filtered {
+                    debug!("{} is hidden by the include/exclude rules", path);
+                }
+                filtered
+            }
+            Ok(None) => false,
+            Err(e) => {
+                error!("unable to resolve path for the include/exclude rules: {}", e);
+                true
+            }
+        }
+    }
+
+    /*
+     * Symlink targets are full ASM paths (+DATA/ORCL/...). Below the mountpoint they lose the
+     * -o root= prefix, and with -o relative_links they are relative to the link's own directory,
+     * so they keep working through bind mounts, NFS and containers.
+     */
+    fn link_target(&self, ino: u64, target: &str) -> Result<String, Error> {
+        let below_mount = match &self.config.root {
+            None => target,
+            Some(root) => match below_root(root, target) {
+                Some(below_mount) => below_mount,
+                // the target is not reachable through this mount; show the ASM path as it is
+                None => return Ok(target.to_string()),
+            },
+        };
+
+        if !self.config.relative_links {
+            return Ok(format!("{}{}", self.config.mount_point, below_mount));
+        }
+
+        let link_path = self.ora()?.query_asm_alias_path(ino)?;
+        let link_dir = link_path.rsplit_once('/').map(|(dir, _)| dir).unwrap_or("");
+        Ok(relative_path(link_dir, target))
+    }
+
+    /*
+     * How a file alias is presented, by default a user alias is a symlink to the system-created name:
+     *
+     *   -o hard_links  any alias is the file itself (the system alias' inode)
+     *   -o hide_omf    a user alias is a regular file of its own; the system-created name is hidden (in SQL)
+     *
+     * The name of the alias has been checked against the include/exclude rules by the caller; a user
+     * alias must pass them under the system-created name too, as its symlink target would have to.
+     */
+    fn present_alias(&self, attr: FileAttr) -> Result<FileAttr, Error> {
+        let inode = Inode::from_ino(attr.ino.0);
+
+        if attr.kind == FileType::Directory || inode.is_synthetic() || inode.is_view() {
+            return Ok(attr);
+        }
+
+        if self.config.hard_links {
+            let file_attr = self.ora()?.query_hard_link_ent_ino(attr.ino.0)?;
+
+            if attr.kind == FileType::Symlink && self.filter_active() && self.is_filtered(self.asm_path(file_attr.ino.0), FileType::RegularFile) {
+                return Err(Error::new(ErrorKind::Other, "asmfs; excluded by the include/exclude rules".to_string()));
+            }
+
+            Ok(file_attr)
+        } else if self.config.hide_omf && attr.kind == FileType::Symlink {
+            if self.filter_active() {
+                let target = self.ora()?.query_asm_alias_link(attr.ino.0);
+                if self.is_filtered(target.map(Some), FileType::RegularFile) {
+                    return Err(Error::new(ErrorKind::Other, "asmfs; excluded by the include/exclude rules".to_string()));
+                }
+            }
+
+            Ok(FileAttr { kind: FileType::RegularFile, ..attr })
+        } else {
+            Ok(attr)
+        }
     }
 }
 
+// +DATA/ORCL, +DATA/ORCL/DATAFILE/USERS.259.1 => DATAFILE/USERS.259.1; None outside the root
+fn below_root<'a>(root: &str, target: &'a str) -> Option<&'a str> {
+    let inside_root = target
+        .get(..root.len())
+        .is_some_and(|prefix| prefix.eq_ignore_ascii_case(root))
+        && target[root.len()..].starts_with('/');
+
+    if inside_root { Some(&target[root.len() + 1..]) } else { None }
+}
+
+// +DATA/ORCL/LINKS, +DATA/ORCL/DATAFILE/USERS.259.1 => ../DATAFILE/USERS.259.1
+fn relative_path(from_dir: &str, to: &str) -> String {
+    let from: Vec<&str> = from_dir.split('/').filter(|c| !c.is_empty()).collect();
+    let to: Vec<&str> = to.split('/').filter(|c| !c.is_empty()).collect();
+    let common = from.iter().zip(&to).take_while(|(a, b)| a == b).count();
+
+    // one ".." for each directory of `from_dir` below the common ancestor
+    let mut components: Vec<&str> = from[common..].iter().map(|_| "..").collect();
+    components.extend(&to[common..]);
+    components.join("/")
+}
+
+fn init_kernel_config(config: &mut KernelConfig) -> std::io::Result<()> {
+    config
+        .add_capabilities(InitFlags::FUSE_EXPORT_SUPPORT)
+        .map_err(|unsupported| {
+            std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                format!("kernel does not support required FUSE capability: {:?}", unsupported)
+            )
+        })?;
+
+    info!("FUSE export support enabled; kernel ABI: {:?}", config.kernel_abi());
+    Ok(())
+}
+
+// the shared metadata connection, set up for this mount; also used by `asmfs ctl reconnect`
+pub(crate) fn connect_metadata(config: &MountConfig, first: usize) -> Result<OracleConnection, String> {
+    let mut ora = OracleConnection::connect(config.connection_string.clone(), &config.failover_conns, first)
+        .map_err(|e| format!("Unable to connect to oracle: {e}"))?;
+
+    if let Some(diskgroups) = &config.diskgroups {
+        ora.restrict_diskgroups(diskgroups.clone());
+    }
+
+    ora.set_ignore_case(config.ignore_case);
+    ora.set_hide_omf(config.hide_omf);
+    Ok(ora)
+}
+
+// walks +GROUP/dir/dir down to the inode of the last directory
+fn resolve_root(ora: &OracleConnection, path: &str) -> Result<u64, Error> {
+    let mut components = path.split('/').filter(|component| !component.is_empty());
+
+    let group = components
+        .next()
+        .ok_or_else(|| Error::new(ErrorKind::Other, "asmfs; empty root path".to_string()))?;
+    let mut attr = ora.query_asm_diskgroup_ent_name(group)?;
+
+    for name in components {
+        attr = ora.query_asm_alias_ent(attr.ino.0, name)?;
+
+        if attr.kind != FileType::Directory {
+            return Err(Error::new(ErrorKind::Other, format!("asmfs; '{}' is not a directory", name)));
+        }
+    }
+
+    Ok(attr.ino.0)
+}
+
 impl Filesystem for AsmFS {
 
     fn init(&mut self, _req: &Request, config: &mut KernelConfig) -> std::io::Result<()> {
-        config
-            .add_capabilities(InitFlags::FUSE_EXPORT_SUPPORT)
-            .map_err(|unsupported| {
-                std::io::Error::new(
-                    std::io::ErrorKind::Unsupported,
-                    format!("kernel does not support required FUSE capability: {:?}", unsupported)
-                )
-            })?;
-
-        info!("FUSE export support enabled; kernel ABI: {:?}", config.kernel_abi());
-        Ok(())
+        init_kernel_config(config)
     }
 
     fn lookup(&self, _req: &Request, parent: INodeNo, name: &OsStr, reply: ReplyEntry) {
         info!("lookup(parent={}, name={:?})", parent, name);
+        let _timer = self.stats.time(Op::Lookup);
+        let parent = self.real_ino(parent);
 
         let contents: Result<FileAttr, Error> = if name == OsStr::new(".") {
             self.resolve_node_attr(parent)
@@ -114,16 +466,26 @@ impl Filesystem for AsmFS {
                 Some(name) => name,
                 None => {
                     error!("lookup(parent={}, name={:?}) failed: name is not valid UTF-8", parent, name);
-                    reply.error(Errno::ENOENT);
+                    reply.error(self.stats.error(Errno::ENOENT));
                     return;
                 }
             };
 
-            if parent.0 == 1 {
+            if self.is_root(parent.0) && name_str == CONTROL_DIR_NAME {
+                self.resolve_node_attr(INodeNo(control_dir_ino()))
+            } else if let Err(e) = self.check_diskgroup(parent.0) {
+                Err(e)
+            } else if Inode::from_ino(parent.0).is_synthetic() {
+                self.synthetic_lookup(parent.0, name_str)
+            } else if parent.0 == 1 {
                 self.ora
                     .lock()
                     .unwrap()
                     .query_asm_diskgroup_ent_name(name_str)
+            } else if Inode::from_ino(parent.0).is_view() {
+                self.view_lookup_child(parent.0, name_str)
+            } else if let Some(view_ino) = view_lookup(parent.0, name_str) {
+                self.resolve_node_attr(INodeNo(view_ino))
             } else {
                 self.ora
                     .lock()
@@ -132,9 +494,29 @@ impl Filesystem for AsmFS {
             }
         };
 
-        // Change the ownership only when lookup succeeded.
+        let contents = contents.and_then(|attr| {
+            if self.filter_active() && self.is_filtered(self.asm_path(self.real_ino(attr.ino).0), attr.kind) {
+                Err(Error::new(ErrorKind::Other, "asmfs; excluded by the include/exclude rules".to_string()))
+            } else {
+                Ok(attr)
+            }
+        });
+
+        let contents = contents.and_then(|attr| self.present_alias(attr));
+
+        // Change the ownership and mode only when lookup succeeded.
         // Any Error value passes through unchanged.
-        let contents = contents.map(|attr| self.with_configured_owner(attr));
+        let denied = match &contents {
+            Ok(attr) => self.policy_check_ino(_req, self.real_ino(attr.ino).0, attr.kind).err(),
+            Err(_) => None,
+        };
+        if let Some(path) = denied {
+            Self::log_denied(_req, "lookup", &path);
+            reply.error(self.stats.error(Errno::EACCES));
+            return;
+        }
+
+        let contents = contents.map(|attr| self.with_configured_access(attr));
 
         match contents {
             Ok(attr) => {
@@ -144,13 +526,14 @@ impl Filesystem for AsmFS {
             }
             Err(e) => {
                 error!("lookup(parent={}, name={:?}) failed: {}", parent, name, e);
-                reply.error(Errno::ENOENT);
+                reply.error(self.stats.error(Errno::ENOENT));
             }
         }
     }
 
     fn getattr(&self, _req: &Request, ino: INodeNo, _fh: Option<FileHandle>, reply: ReplyAttr) {
         info!("getattr(ino={})", ino);
+        let _timer = self.stats.time(Op::Getattr);
 
         match self.resolve_node_attr(ino) {
             Ok(attr) => {
@@ -159,22 +542,25 @@ impl Filesystem for AsmFS {
             }
             Err(e) => {
                 error!("getattr(ino={}) failed: {}", ino, e);
-                reply.error(Errno::ENOENT);
+                reply.error(self.stats.error(Errno::ENOENT));
             }
         }
     }
 
     fn readlink(&self, _req: &Request, ino: INodeNo, reply: ReplyData) {
         info!("readlink(ino={})", ino);
-        match self.ora.lock().unwrap().query_asm_alias_link(ino.0) {
-            Ok(target) => {
-                let abs_target: String = format!("{}{}", self.mount_point, target);
+        let _timer = self.stats.time(Op::Readlink);
+        let target = self
+            .check_diskgroup(ino.0)
+            .and_then(|()| self.ora()?.query_asm_alias_link(ino.0));
+        match target.and_then(|target| self.link_target(ino.0, &target)) {
+            Ok(abs_target) => {
                 debug!(".. readlink() ok, target={}", abs_target);
                 reply.data(abs_target.as_bytes());
             },
             Err(e) => {
                 error!(".. readlink() failed: {}", e);
-                reply.error(Errno::ENOENT);
+                reply.error(self.stats.error(Errno::ENOENT));
                 return;
             }
         };
@@ -182,62 +568,127 @@ impl Filesystem for AsmFS {
 
     fn open(&self, _req: &Request, ino: INodeNo, _flags: OpenFlags, reply: ReplyOpen) {
         info!("open(ino={})", ino);
+        let _timer = self.stats.time(Op::Open);
+        let started = Instant::now();
 
-        if self.use_raw {
-            self.open_raw(_req, ino.0, _flags, reply);
-        } else {
-            self.open_dbms(_req, ino.0, _flags, reply);
+        let policy = self.policy_check_ino(_req, ino.0, FileType::RegularFile);
+        if let Err(path) = &policy {
+            Self::log_denied(_req, "open", path);
+        }
+
+        let result = match policy {
+            Err(_) => Err(Errno::EACCES),
+            Ok(()) if is_control_file(ino.0) => return self.open_control(ino.0, reply),
+            // a whole disk holds the excluded files too, byte for byte
+            Ok(()) if is_disk_device(ino.0) && self.filter_active() => {
+                error!(".. open() refused: disk devices cannot be read while include/exclude rules are active");
+                Err(Errno::EACCES)
+            },
+            Ok(()) if is_disk_device(ino.0) => self.open_disk(ino.0),
+            Ok(()) if self.filter_active() && self.is_filtered(self.asm_path(ino.0), FileType::RegularFile) => Err(Errno::ENOENT),
+            Ok(()) if self.config.use_raw => self.open_raw(ino.0),
+            Ok(()) => self.open_dbms(ino.0),
+        };
+
+        if let Some(audit) = &self.audit {
+            self.audit_open(audit, _req, ino.0, started, result);
+        }
+
+        match result {
+            Ok(fh) => reply.opened(FileHandle(fh), FopenFlags::empty()),
+            Err(errno) => reply.error(self.stats.error(errno)),
         }
     }
 
     fn read(&self, _req: &Request, ino: INodeNo, fh: FileHandle, offset: u64, size: u32, _flags: OpenFlags, _lock: Option<LockOwner>, reply: ReplyData) {
         // info!("read(ino={}, _fh={}, offset={}, _size={}, flags={})", ino, fh, offset, size, _flags);
-        if self.use_raw {
+        let _timer = self.stats.time(Op::Read);
+
+        if is_control_file(ino.0) {
+            self.read_control(fh.0, offset, size, reply);
+            return;
+        }
+
+        let result = if self.config.use_raw || is_disk_device(ino.0) {
             let handle = {
                 let guard = self.handles_raw.read().unwrap();
                 match guard.get(&fh.0) {
                     Some(h) => Arc::clone(h),
-                    None => {reply.error(Errno::EBADF); return;}
+                    None => {reply.error(self.stats.error(Errno::EBADF)); return;}
                 }
             };
 
             if handle.striped == ASM_STRIPED_COARSE {
-                self.read_raw_coarse(handle, offset, size, reply);
+                self.read_raw_coarse(fh.0, handle, offset, size, _req.uid())
             } else if handle.striped == ASM_STRIPED_FINE {
-                self.read_raw_fine(handle, offset, size, reply);
+                self.read_raw_fine(fh.0, handle, offset, size, _req.uid())
             } else {
                 error!("Unsupported stripped mode: {}", handle.striped);
-                reply.error(Errno::EINVAL);
+                Err(Errno::EINVAL)
             }
         } else {
-            self.read_dbms(_req, ino.0, fh.0, offset, size, _flags, _lock, reply);
+            self.read_dbms(_req, ino.0, fh.0, offset, size, _flags, _lock)
+        };
+
+        match result {
+            Ok(buffer) => {
+                if let Some(audit) = &self.audit {
+                    audit.read(ino.0, fh.0, buffer.len());
+                }
+                reply.data(&buffer);
+            },
+            Err(errno) => reply.error(self.stats.error(errno)),
         }
     }
 
     fn release(&self, _req: &Request, ino: INodeNo, fh: FileHandle, _flags: OpenFlags, _lock_owner: Option<LockOwner>, _flush: bool, reply: ReplyEmpty) {
         info!("release(fh={})", fh);
+        let _timer = self.stats.time(Op::Release);
+
+        if is_control_file(ino.0) {
+            self.handles_control.lock().unwrap().remove(&fh.0);
+            reply.ok();
+            return;
+        }
 
-        if self.use_raw {
-            self.release_raw(_req, ino.0, reply);
+        let result = if self.config.use_raw || is_disk_device(ino.0) {
+            self.release_raw(fh.0)
         } else {
-            self.release_dbms(_req, fh.0, reply);
+            self.release_dbms(fh.0)
+        };
+
+        if let Some(audit) = &self.audit {
+            audit.released(ino.0, fh.0, result);
+        }
+
+        match result {
+            Ok(()) => reply.ok(),
+            Err(errno) => reply.error(self.stats.error(errno)),
         }
     }
 
     fn readdir(&self, _req: &Request, ino: INodeNo, _fh: FileHandle, offset: u64, mut reply: ReplyDirectory) {
         info!("readdir(ino={}, offset={}, fh={})", ino, offset, _fh);
+        let _timer = self.stats.time(Op::Readdir);
+        let ino = self.real_ino(ino);
 
         let attr = match self.resolve_node_attr(ino) {
             Ok(attr) => attr,
             Err(e) => {
                 error!("readdir(ino={}) failed to resolve inode: {}", ino, e);
-                reply.error(Errno::ENOENT);
+                reply.error(self.stats.error(Errno::ENOENT));
                 return;
             }
         };
 
         if attr.kind != FileType::Directory {
-            reply.error(Errno::ENOTDIR);
+            reply.error(self.stats.error(Errno::ENOTDIR));
+            return;
+        }
+
+        if let Err(path) = self.policy_check_ino(_req, ino.0, FileType::Directory) {
+            Self::log_denied(_req, "readdir", &path);
+            reply.error(self.stats.error(Errno::EACCES));
             return;
         }
 
@@ -245,7 +696,7 @@ impl Filesystem for AsmFS {
             Ok(parent_ino) => parent_ino,
             Err(e) => {
                 error!("readdir(ino={}) failed to resolve parent: {}", ino, e);
-                reply.error(Errno::ENOENT);
+                reply.error(self.stats.error(Errno::ENOENT));
                 return;
             }
         };
@@ -256,26 +707,81 @@ impl Filesystem for AsmFS {
                     .lock()
                     .unwrap()
                     .query_asm_diskgroup_vec()
+            } else if Inode::from_ino(ino.0).is_synthetic() {
+                self.synthetic_children(ino.0)
+            } else if Inode::from_ino(ino.0).is_view() {
+                self.view_children(ino.0)
             } else {
+                let inode = Inode::from_ino(ino.0);
                 self.ora
                     .lock()
                     .unwrap()
                     .query_asm_alias_vec(ino.0)
+                    .map(|mut children| {
+                        if inode.is_disk_group() {
+                            children.extend(view_entries(inode.get_group_number()));
+                        }
+                        children
+                    })
             };
 
-        let children = match contents {
+        let mut children = match contents {
             Ok(children) => children,
             Err(e) => {
                 error!("readdir(ino={}) failed to list children: {}", ino, e);
-                reply.error(Errno::ENOENT);
+                reply.error(self.stats.error(Errno::ENOENT));
                 return;
             }
         };
 
+        if self.filter_active() {
+            let dir_path = match self.asm_path(ino.0) {
+                Ok(dir_path) => dir_path,
+                Err(e) => {
+                    error!("readdir(ino={}) failed to resolve path: {}", ino, e);
+                    reply.error(self.stats.error(Errno::ENOENT));
+                    return;
+                }
+            };
+            children.retain(|(child_ino, kind, name)| !self.is_filtered(self.child_path(dir_path.as_deref(), *child_ino, name), *kind));
+        }
+
+        if self.config.hard_links || self.config.hide_omf {
+            children = children
+                .into_iter()
+                .filter_map(|(child_ino, kind, name)| {
+                    if kind != FileType::Symlink {
+                        return Some((child_ino, kind, name));
+                    }
+                    // an alias whose file is hidden or gone is left out
+                    let attr = self.ora().ok()?.query_asm_alias_ent_ino(child_ino).ok()?;
+                    self.present_alias(attr).ok().map(|attr| (attr.ino.0, attr.kind, name))
+                })
+                .collect();
+        }
+
+        if self.is_root(ino.0) {
+            children.push((control_dir_ino(), FileType::Directory, CONTROL_DIR_NAME.to_string()));
+        }
+
+        // entries the caller may not access are not listed either
+        if self.policy_active() {
+            let dir_path = self.asm_path(ino.0).unwrap_or(None);
+            children.retain(|(child_ino, kind, name)| {
+                let child = Inode::from_ino(*child_ino);
+                let path = if child.is_synthetic() || child.is_view() {
+                    self.policy_path(*child_ino)
+                } else {
+                    self.child_path(dir_path.as_deref(), *child_ino, name)
+                };
+                self.policy_check(_req, path, *kind).is_ok()
+            });
+        }
+
         let mut entries = Vec::with_capacity(children.len() + 2);
 
-        entries.push((ino.0, FileType::Directory, ".".to_string()));
-        entries.push((parent_ino.0, FileType::Directory, "..".to_string()));
+        entries.push((self.kernel_ino(ino.0), FileType::Directory, ".".to_string()));
+        entries.push((self.kernel_ino(parent_ino.0), FileType::Directory, "..".to_string()));
         entries.extend(children);
 
         // Positional offsets are valid while the directory contents remain unchanged.
@@ -291,8 +797,50 @@ impl Filesystem for AsmFS {
         reply.ok();
     }
 
+    fn statfs(&self, _req: &Request, ino: INodeNo, reply: ReplyStatfs) {
+        info!("statfs(ino={})", ino);
+        let _timer = self.stats.time(Op::Statfs);
+        let ino = self.real_ino(ino);
+
+        let diskgroups = match self.ora().unwrap().query_asm_diskgroup_space() {
+            Ok(diskgroups) => diskgroups,
+            Err(e) => {
+                error!("statfs(ino={}) failed: {}", ino, e);
+                reply.error(self.stats.error(Errno::EIO));
+                return;
+            }
+        };
+
+        // Root and /.asmfs/ report the sum of all mounted groups, in MB blocks (the unit of v$asm_diskgroup);
+        // with -o root= /.asmfs/ reports the group of the root only, like its contents.
+        // Anything inside a diskgroup reports that group alone, in AU sized blocks.
+        let inode = Inode::from_ino(ino.0);
+        let (selected, block_size): (Vec<_>, u64) = if ino.0 == 1 || inode.is_synthetic() {
+            (diskgroups.iter().filter(|dg| self.shows_diskgroup(dg.group_number)).collect(), 1024 * 1024)
+        } else {
+            let selected: Vec<_> = diskgroups.iter().filter(|dg| dg.group_number == inode.get_group_number()).collect();
+            let block_size = selected.first().map(|dg| dg.au_size as u64).unwrap_or(1024 * 1024);
+            (selected, block_size)
+        };
+
+        if selected.is_empty() && ino.0 != 1 && !inode.is_synthetic() {
+            error!("statfs(ino={}) failed: group {} is not mounted", ino, inode.get_group_number());
+            reply.error(self.stats.error(Errno::ENOENT));
+            return;
+        }
+
+        let to_blocks = |mb: u64| mb * 1024 * 1024 / block_size;
+        let blocks = selected.iter().map(|dg| to_blocks(dg.total_mb)).sum();
+        let bfree = selected.iter().map(|dg| to_blocks(dg.free_mb)).sum();
+        let bavail = selected.iter().map(|dg| to_blocks(dg.usable_file_mb)).sum();
+        let files = selected.iter().map(|dg| dg.files).sum();
+
+        reply.statfs(blocks, bfree, bavail, files, 0, block_size as u32, 255, block_size as u32);
+    }
+
     fn getxattr(&self, _req: &Request, _ino: INodeNo, _name: &OsStr, _size: u32, reply: ReplyXattr) {
         // ASMFS has no extended attributes; but NFSv4.2 expects this to be implemented.
+        // Not counted by stats.error(): every probe (security.capability, ACLs) gets this answer.
         reply.error(Errno::ENODATA);
     }
 
@@ -319,10 +867,12 @@ impl Filesystem for AsmFS {
 
 impl AsmFS {
     fn resolve_node_attr(&self, ino: INodeNo) -> Result<FileAttr, Error> {
+        let ino = self.real_ino(ino);
+
         if ino.0 == 1 {
             // root:
             let time = synthetic_dir_time();
-            return Ok(FileAttr {
+            return Ok(self.with_configured_access(FileAttr {
                 ino: INodeNo(1),
                 size: 0,
                 blocks: 0,
@@ -333,32 +883,57 @@ impl AsmFS {
                 kind: FileType::Directory,
                 perm: 0o755,
                 nlink: 2,
-                uid: self.owner_uid,
-                gid: self.owner_gid,
+                uid: self.config.owner_uid,
+                gid: self.config.owner_gid,
                 rdev: 0,
                 flags: 0,
-                blksize: 512});
+                blksize: 512}));
         }
 
         let inode = Inode::from_ino(ino.0);
-        let ora = self.ora.lock()?;
 
-        let attr = if inode.is_disk_group() {
-            ora.query_asm_diskgroup_ent_ino(ino.0)?
+        if inode.is_synthetic() {
+            return self.synthetic_attr(ino.0).map(|attr| self.with_configured_access(attr));
+        }
+
+        // readdir() resolves its directory here first, so this covers listings too
+        self.check_diskgroup(ino.0)?;
+
+        if inode.is_view() {
+            return self.view_attr(ino.0).map(|attr| self.with_configured_access(attr));
+        }
+
+        let mut attr = if inode.is_disk_group() {
+            self.ora()?.query_asm_diskgroup_ent_ino(ino.0)?
         } else {
-            ora.query_asm_alias_ent_ino(ino.0)?
+            let attr = self.ora()?.query_asm_alias_ent_ino(ino.0)?;
+            self.present_alias(attr)?
         };
+        attr.ino = INodeNo(self.kernel_ino(attr.ino.0));
 
-        Ok(self.with_configured_owner(attr))
+        Ok(self.with_configured_access(attr))
     }
 
     fn resolve_parent_ino(&self, ino: INodeNo) -> Result<INodeNo, Error> {
-        if ino.0 == 1 {
-            return Ok(INodeNo(1));
+        let ino = self.real_ino(ino);
+
+        // ".." of the root is the root itself, also when the root is an ASM directory (-o root=)
+        if ino.0 == 1 || self.is_root(ino.0) {
+            return Ok(ino);
         }
 
         let inode = Inode::from_ino(ino.0);
 
+        if inode.is_synthetic() {
+            self.resolve_node_attr(ino)?;
+            return self.synthetic_parent_ino(ino.0);
+        }
+
+        if inode.is_view() {
+            self.resolve_node_attr(ino)?;
+            return self.view_parent_ino(ino.0);
+        }
+
         if inode.is_disk_group() {
             // Validate that this is an existing disk group, not merely a correctly shaped inode.
             self.resolve_node_attr(ino)?;
@@ -374,102 +949,222 @@ impl AsmFS {
         }
     }
 
-    fn open_dbms(&self, _req: &Request, ino: u64, _flags: OpenFlags, reply: ReplyOpen) {
-        // each call to open() establishes new connection
-        let conn = match OracleConnection::connect(self.connection_string.clone()) {
+    fn open_dbms(&self, ino: u64) -> Result<u64, Errno> {
+        let handle = self.open_dbms_handle(ino)?;
+        let fh = handle.handle;
+
+        self.handles_dbms.lock().unwrap().insert(fh, Arc::new(Mutex::new(handle)));
+
+        debug!(".. open() ok, fh={}", fh);
+        Ok(fh)
+    }
+
+    fn open_dbms_handle(&self, ino: u64) -> Result<OpenFileHandle, Errno> {
+        // the per-open connection below is not restricted, so the diskgroup filter is checked here
+        let target = {
+            let ora = self.ora().unwrap();
+            if let Err(e) = ora.check_diskgroup_number(Inode::from_ino(ino).get_group_number()) {
+                error!(".. open() refused: {}", e);
+                return Err(Errno::ENOENT);
+            }
+            ora.target()
+        };
+
+        // each call to open() establishes new connection, to the instance the metadata connection uses
+        let conn = match OracleConnection::connect(self.config.connection_string.clone(), &self.config.failover_conns, target) {
             Ok(ora) => ora,
             Err(e) => {
                 error!("open() failed establishing new connection: {}", e);
-                reply.error(Errno::ENOENT);
-                return;
+                return Err(Errno::ENOENT);
             }
         };
 
         match conn.proc_open(ino) {
-            Ok(data) => {
-                let handle = OpenFileHandle {
-                    conn,
-                    block_size: data.1,
-                    blocks_asm: data.2,
-                    blocks_fs: data.3,
-                    file_type: data.4
-                };
-
-                self.handles_dbms.lock().unwrap().insert(data.0, handle);
-
-                reply.opened(FileHandle(data.0), FopenFlags::empty());
-                debug!(".. open() ok, fh={}", data.0);
-            },
+            Ok(data) => Ok(OpenFileHandle {
+                conn,
+                handle: data.0,
+                ino,
+                block_size: data.1,
+                blocks_asm: data.2,
+                blocks_fs: data.3,
+                file_type: data.4
+            }),
             Err(e) => {
                 error!(".. open() failed: {}", e);
-                reply.error(Errno::ENOENT);
+                Err(Errno::ENOENT)
             }
         }
     }
 
-    fn open_raw(&self, _req: &Request, ino: u64, _flags: OpenFlags, reply: ReplyOpen) {
-        let h = self.ora.lock().unwrap().proc_open_raw(ino, self.mirror);
+    fn open_raw(&self, ino: u64) -> Result<u64, Errno> {
+        let h = self.ora().unwrap().proc_open_raw(ino, self.config.mirror, self.config.hybrid);
         match h {
             Ok(handle) => {
                 let file_number :u32 = handle.file_number;
+                let fh = self.next_fh.fetch_add(1, Ordering::Relaxed);
 
                 self.handles_raw
                     .write()
                     .unwrap()
-                    .insert(ino, Arc::new(handle));
+                    .insert(fh, Arc::new(handle));
 
-                debug!(".. open() ok, fh={}, file_number={}", ino, file_number);
-                reply.opened(FileHandle(ino), FopenFlags::empty());
+                debug!(".. open() ok, fh={}, file_number={}", fh, file_number);
+                Ok(fh)
             },
             Err(e) => {
                 error!(".. open() failed: {}", e);
-                reply.error(Errno::ENOENT)
+                Err(Errno::ENOENT)
             }
         }
     }
 
-    fn release_dbms(&self, _req: &Request, fh: u64, reply: ReplyEmpty) {
-        let mut guard = self.handles_dbms.lock().unwrap();
-        let handle = guard.get(&fh).unwrap();
-        match handle.conn.proc_close(fh) {
-            Ok(()) => {
-                reply.ok();
-                debug!(".. release() ok");
+    fn open_control(&self, ino: u64, reply: ReplyOpen) {
+        match self.render_control_file(ino) {
+            Ok(content) => {
+                let fh = self.next_fh.fetch_add(1, Ordering::Relaxed);
+                self.handles_control.lock().unwrap().insert(fh, content.into_bytes());
+
+                // size is reported as 0, so the page cache must not be used for these files
+                reply.opened(FileHandle(fh), FopenFlags::FOPEN_DIRECT_IO);
+                debug!(".. open() ok, control fh={}", fh);
             },
             Err(e) => {
-                error!(".. release() failed: {}", e);
+                error!(".. open() failed to render control file: {}", e);
+                reply.error(self.stats.error(Errno::EIO));
             }
         }
-        guard.remove(&fh);
     }
 
-    fn release_raw(&self, _req: &Request<>, ino: u64, reply: ReplyEmpty) {
-        self.handles_raw.write().unwrap().remove(&ino);
-        reply.ok();
-        debug!(".. release() ok");
+    fn open_disk(&self, ino: u64) -> Result<u64, Errno> {
+        match self.open_disk_device(ino) {
+            Ok(handle) => {
+                let fh = self.next_fh.fetch_add(1, Ordering::Relaxed);
+
+                self.handles_raw
+                    .write()
+                    .unwrap()
+                    .insert(fh, Arc::new(handle));
+
+                debug!(".. open() ok, disk device fh={}", fh);
+                Ok(fh)
+            },
+            Err(e) => {
+                error!(".. open() failed: {}", e);
+                Err(Errno::EIO)
+            }
+        }
+    }
+
+    fn read_control(&self, fh: u64, offset: u64, size: u32, reply: ReplyData) {
+        let guard = self.handles_control.lock().unwrap();
+        let Some(content) = guard.get(&fh) else {
+            reply.error(self.stats.error(Errno::EBADF));
+            return;
+        };
+
+        let start = std::cmp::min(offset as usize, content.len());
+        let end = std::cmp::min(start + size as usize, content.len());
+        reply.data(&content[start..end]);
     }
 
-    fn read_dbms(&self, _req: &Request, _ino: u64, fh: u64, offset: u64, size: u32, _flags: OpenFlags, _lock: Option<LockOwner>, reply: ReplyData) {
-        let guard = self.handles_dbms.lock().unwrap();
-        let handle = guard.get(&fh).unwrap();
+    fn release_dbms(&self, fh: u64) -> Result<(), Errno> {
+        let handle = self.handles_dbms.lock().unwrap().remove(&fh).ok_or(Errno::EBADF)?;
+        let handle = handle.lock().unwrap();
+        close_dbms_handle(&handle)
+    }
+
+    fn release_raw(&self, fh: u64) -> Result<(), Errno> {
+        self.handles_raw.write().unwrap().remove(&fh);
+
+        // the dbms_diskgroup handle of -o hybrid, if a gap was read at all
+        let gap = self.handles_gap.lock().unwrap().remove(&fh);
+        match gap.and_then(|gap| gap.lock().unwrap().take()) {
+            Some(handle) => close_dbms_handle(&handle),
+            None => {
+                debug!(".. release() ok");
+                Ok(())
+            }
+        }
+    }
+
+    fn read_dbms(&self, _req: &Request, _ino: u64, fh: u64, offset: u64, size: u32, _flags: OpenFlags, _lock: Option<LockOwner>) -> Result<Vec<u8>, Errno> {
+        // the map is not locked during the read: a failover may take a connect timeout per instance
+        let handle = Arc::clone(self.handles_dbms.lock().unwrap().get(&fh).ok_or(Errno::EBADF)?);
+        let mut handle = handle.lock().unwrap();
 
         let magic_constant = self.header_fix_constant_when_needed_int(handle.file_type, offset);
+        let result = self.read_dbms_handle(&mut handle, offset, size, magic_constant);
 
-        match handle.conn.proc_read(fh, offset, size, handle.block_size, handle.bytes_size_fs(), handle.bytes_size_asm(), handle.file_type, magic_constant) {
+        match result {
             Ok(buffer) => {
-                reply.data(buffer.as_slice());
+                self.stats.add_dbms_bytes(buffer.len());
                 debug!(".. read() ok, offset={}, size={}", offset, size);
+                Ok(buffer)
             },
             Err(e) => {
                 error!("read() failed: {}", e);
-                reply.error(Errno::ENOENT);
+                Err(Errno::ENOENT)
+            }
+        }
+    }
+
+    fn read_dbms_handle(&self, handle: &mut OpenFileHandle, offset: u64, size: u32, magic_constant: Option<u32>) -> Result<Vec<u8>, Error> {
+        let read = |handle: &OpenFileHandle| handle.conn.proc_read(handle.handle, offset, size, handle.block_size, handle.bytes_size_fs(), handle.bytes_size_asm(), handle.file_type, magic_constant);
+
+        let mut result = read(handle);
+
+        // the instance of this session is gone: the same file on the next instance, the same read again
+        if result.is_err() && handle.conn.is_lost() {
+            match self.reopen_dbms(handle) {
+                Ok(()) => result = read(handle),
+                Err(e) => error!("read() failover failed: {}", e),
             }
         }
+
+        result
+    }
+
+    // opens the file of `handle` again, in a new session on the next instance; the fh stays
+    fn reopen_dbms(&self, handle: &mut OpenFileHandle) -> Result<(), Error> {
+        let conn = OracleConnection::connect(self.config.connection_string.clone(), &self.config.failover_conns, handle.conn.target() + 1)?;
+        let data = conn.proc_open(handle.ino)?;
+
+        info!("Failed over open file {} from {} to {}", handle.ino, handle.conn.target_name(), conn.target_name());
+        handle.conn = conn;
+        handle.handle = data.0;
+        self.stats.reconnects.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /*
+     * -o hybrid: `buffer` holds file bytes from `offset` on that lie on disks this host could not
+     * open. They are read through dbms_diskgroup, with a handle opened on the first gap of `fh`
+     * and kept until its release. Only that handle is locked for the read, so gaps of other open
+     * files are read in parallel. A gap at offset 0 gets the same header fix as the raw read.
+     */
+    fn read_gap(&self, fh: u64, ino: u64, offset: u64, buffer: &mut [u8]) -> Result<(), Errno> {
+        let gap = Arc::clone(self.handles_gap.lock().unwrap().entry(fh).or_default());
+        let mut gap = gap.lock().unwrap();
+        if gap.is_none() {
+            *gap = Some(self.open_dbms_handle(ino)?);
+        }
+        let handle = gap.as_mut().unwrap();
+
+        let block_size = handle.block_size as u64;
+        let read = |block_offset, bytes| {
+            let magic_constant = self.header_fix_constant_when_needed_int(handle.file_type, block_offset);
+            self.read_dbms_handle(handle, block_offset, bytes, magic_constant)
+        };
+
+        read_whole_blocks(buffer, offset, block_size, read).map_err(|e| {
+            error!("read() of gap failed: {}", e);
+            Errno::EIO
+        })
     }
 
     // used by raw path
     fn header_fix_constant_when_needed(&self, handle: &RawOpenFileHandle, offset: u64) -> Option<u32> {
-        if !self.magic || offset != 0 {
+        if !self.config.magic || offset != 0 {
             return None;
         }
 
@@ -485,7 +1180,7 @@ impl AsmFS {
 
     // used by --no-raw (dbms_diskgroup) path
     fn header_fix_constant_when_needed_int(&self, file_type_int: u32, offset: u64) -> Option<u32> {
-        if !self.magic || offset != 0 {
+        if !self.config.magic || offset != 0 {
             return None;
         }
 
@@ -499,7 +1194,7 @@ impl AsmFS {
             .map(|(_, magic_constant, _, _, _)| *magic_constant)
     }
 
-    fn read_raw_fine(&self, handle: Arc<RawOpenFileHandle>, offset: u64, bytes_requested: u32, reply: ReplyData) {
+    fn read_raw_fine(&self, fh: u64, handle: Arc<RawOpenFileHandle>, offset: u64, bytes_requested: u32, uid: u32) -> Result<Vec<u8>, Errno> {
         // clamp requested size to file size
         let size: usize = {
             let s = bytes_requested as u64;
@@ -512,10 +1207,11 @@ impl AsmFS {
         };
 
         if size == 0 {
-            reply.data(&[]);
-            return;
+            return Ok(Vec::new());
         }
 
+        self.throttle.acquire(handle.group_number, uid, size);
+
         let mut buffer = vec![0u8; size];
 
         // Then loop one stripe-chunk at a time:
@@ -526,6 +1222,7 @@ impl AsmFS {
         let round_size = stripe_count * stripe_width;    // = SC*SW = 1 MB here, regardless of AU (128kb*8 = 1024kb = 1mb)
 
         let mut bytes_read :usize = 0;
+        let mut gaps: Vec<(usize, usize)> = Vec::new(); // (buffer offset, length) on disks not in disk_list
         while bytes_read < size {
             let file_off = offset + bytes_read as u64;
             let ve = file_off / ve_size;
@@ -544,32 +1241,42 @@ impl AsmFS {
             let idx = (ve * stripe_count + stripe) as usize; // most likely wrong
             if idx >= handle.au_list.len() {
                 error!("Trying to reach index {} which does not exist in au_list (size={})", idx, handle.au_list.len());
-                reply.error(Errno::EIO);
-                return;
+                return Err(Errno::EIO);
             }
 
             let (disk_no, au_no) = handle.au_list[idx];
-            let disk = handle.disk_list.get(&disk_no).unwrap();
             let disk_off = au_no as u64 * au_size + round * stripe_width + in_stripe;
             let chunk= std::cmp::min(stripe_width - in_stripe, (size - bytes_read) as u64) as usize;
 
-            disk.read_exact_at(&mut buffer[bytes_read..bytes_read + chunk], disk_off)
-                .expect("read_exact_at() failed");
+            match handle.disk_list.get(&disk_no) {
+                Some(disk) => {
+                    disk.read_exact_at(&mut buffer[bytes_read..bytes_read + chunk], disk_off)
+                        .expect("read_exact_at() failed");
+                    self.stats.add_disk_bytes(handle.group_number, disk_no, chunk);
+                },
+                None => {
+                    add_gap(&mut gaps, bytes_read, chunk);
+                    self.stats.add_gap_bytes(handle.group_number, disk_no, chunk);
+                }
+            }
             bytes_read += chunk;
         }
 
+        for (start, len) in gaps {
+            self.read_gap(fh, handle.ino, offset + start as u64, &mut buffer[start..start + len])?;
+        }
+
         if let Some(magic_constant) = self.header_fix_constant_when_needed(&handle, offset) {
             if let Err(e) = fix_header_block(&mut buffer, magic_constant) {
                 error!(".. read_raw_fine() failed to fix header block: {}", e);
-                reply.error(Errno::ENOENT);
-                return;
+                return Err(Errno::ENOENT);
             }
         }
 
-        reply.data(&buffer);
+        Ok(buffer)
     }
 
-    fn read_raw_coarse(&self, handle: Arc<RawOpenFileHandle>, offset: u64, bytes_requested: u32, reply: ReplyData) {
+    fn read_raw_coarse(&self, fh: u64, handle: Arc<RawOpenFileHandle>, offset: u64, bytes_requested: u32, uid: u32) -> Result<Vec<u8>, Errno> {
 
         // clamp requested size to file size
         let size: usize = {
@@ -583,10 +1290,11 @@ impl AsmFS {
         };
 
         if size == 0 {
-            reply.data(&[]);
-            return;
+            return Ok(Vec::new());
         }
 
+        self.throttle.acquire(handle.group_number, uid, size);
+
         let au_size = handle.au_size as u64;
         let au_first = offset / au_size;
         let au_last  = (offset + size as u64 - 1) / au_size;
@@ -594,11 +1302,11 @@ impl AsmFS {
         // single allocation for the whole reply
         let mut buffer = vec![0u8; size];
         let mut bytes_read: usize = 0;
+        let mut gaps: Vec<(usize, usize)> = Vec::new(); // (buffer offset, length) on disks not in disk_list
 
         if au_last as usize >= handle.au_list.len() {
             error!("AU {} not found in extent map (map len={}, file_number={})", au_last, handle.au_list.len(), handle.file_number);
-            reply.error(Errno::EIO);
-            return;
+            return Err(Errno::EIO);
         }
 
         for au_index in au_first..=au_last {
@@ -615,24 +1323,208 @@ impl AsmFS {
             let chunk_len = std::cmp::min(au_remaining, still_needed);
 
             let au_entry = handle.au_list[au_index as usize];
-            let file_handle = handle.disk_list.get(&au_entry.0).unwrap();
             let disk_offset = au_entry.1 as u64 * au_size + first_byte as u64;
 
-            file_handle
-                .read_exact_at(&mut buffer[bytes_read..bytes_read + chunk_len], disk_offset)
-                .expect("read_exact_at() failed");
+            match handle.disk_list.get(&au_entry.0) {
+                Some(file_handle) => {
+                    file_handle
+                        .read_exact_at(&mut buffer[bytes_read..bytes_read + chunk_len], disk_offset)
+                        .expect("read_exact_at() failed");
+                    self.stats.add_disk_bytes(handle.group_number, au_entry.0, chunk_len);
+                },
+                None => {
+                    add_gap(&mut gaps, bytes_read, chunk_len);
+                    self.stats.add_gap_bytes(handle.group_number, au_entry.0, chunk_len);
+                }
+            }
 
             bytes_read += chunk_len;
         }
 
+        for (start, len) in gaps {
+            self.read_gap(fh, handle.ino, offset + start as u64, &mut buffer[start..start + len])?;
+        }
+
         if let Some(magic_constant) = self.header_fix_constant_when_needed(&handle, offset) {
             if let Err(e) = fix_header_block(&mut buffer, magic_constant) {
                 error!(".. read_raw_coarse() failed to fix header block: {}", e);
-                reply.error(Errno::ENOENT);
-                return;
+                return Err(Errno::ENOENT);
             }
         }
 
-        reply.data(&buffer);
+        Ok(buffer)
+    }
+}
+
+fn close_dbms_handle(handle: &OpenFileHandle) -> Result<(), Errno> {
+    // the file was closed with the session that is gone
+    if handle.conn.is_lost() {
+        debug!(".. release() ok, connection lost");
+        return Ok(());
+    }
+
+    match handle.conn.proc_close(handle.handle) {
+        Ok(()) => {
+            debug!(".. release() ok");
+            Ok(())
+        },
+        Err(e) => {
+            error!(".. release() failed: {}", e);
+            Err(Errno::EIO)
+        }
+    }
+}
+
+/*
+ * proc_read() reads whole blocks: it starts at the beginning of the block `offset` falls in. A
+ * gap of a raw read can start anywhere, e.g. at 4K into an 8K block, so it is read from the
+ * start of its first block and the bytes asked for are copied out of that.
+ */
+fn read_whole_blocks<F>(buffer: &mut [u8], offset: u64, block_size: u64, read: F) -> Result<(), String>
+where
+    F: FnOnce(u64, u32) -> Result<Vec<u8>, Error>,
+{
+    let head = (offset % block_size) as usize;
+    let end = head + buffer.len();
+
+    let data = read(offset - head as u64, end.next_multiple_of(block_size as usize) as u32).map_err(|e| e.to_string())?;
+
+    match data.get(head..end) {
+        Some(wanted) => {
+            buffer.copy_from_slice(wanted);
+            Ok(())
+        },
+        None => Err(format!("{} bytes instead of {} at offset {}", data.len(), end, offset - head as u64)),
+    }
+}
+
+// adjacent gaps are read with one dbms_diskgroup call
+fn add_gap(gaps: &mut Vec<(usize, usize)>, start: usize, len: usize) {
+    match gaps.last_mut() {
+        Some((gap_start, gap_len)) if *gap_start + *gap_len == start => *gap_len += len,
+        _ => gaps.push((start, len)),
+    }
+}
+
+/**
+ * The filesystem as handed to the FUSE session. The session takes ownership of it, while the
+ * control socket (ctl.rs) needs the same AsmFS, so it is shared through an Arc and every
+ * request is passed on.
+ */
+pub struct SharedAsmFS(pub Arc<AsmFS>);
+
+impl Filesystem for SharedAsmFS {
+    fn init(&mut self, _req: &Request, config: &mut KernelConfig) -> std::io::Result<()> {
+        init_kernel_config(config)
+    }
+
+    fn lookup(&self, req: &Request, parent: INodeNo, name: &OsStr, reply: ReplyEntry) {
+        self.0.lookup(req, parent, name, reply)
+    }
+
+    fn getattr(&self, req: &Request, ino: INodeNo, fh: Option<FileHandle>, reply: ReplyAttr) {
+        self.0.getattr(req, ino, fh, reply)
+    }
+
+    fn readlink(&self, req: &Request, ino: INodeNo, reply: ReplyData) {
+        self.0.readlink(req, ino, reply)
+    }
+
+    fn open(&self, req: &Request, ino: INodeNo, flags: OpenFlags, reply: ReplyOpen) {
+        self.0.open(req, ino, flags, reply)
+    }
+
+    fn read(&self, req: &Request, ino: INodeNo, fh: FileHandle, offset: u64, size: u32, flags: OpenFlags, lock: Option<LockOwner>, reply: ReplyData) {
+        self.0.read(req, ino, fh, offset, size, flags, lock, reply)
+    }
+
+    fn release(&self, req: &Request, ino: INodeNo, fh: FileHandle, flags: OpenFlags, lock_owner: Option<LockOwner>, flush: bool, reply: ReplyEmpty) {
+        self.0.release(req, ino, fh, flags, lock_owner, flush, reply)
+    }
+
+    fn readdir(&self, req: &Request, ino: INodeNo, fh: FileHandle, offset: u64, reply: ReplyDirectory) {
+        self.0.readdir(req, ino, fh, offset, reply)
+    }
+
+    fn statfs(&self, req: &Request, ino: INodeNo, reply: ReplyStatfs) {
+        self.0.statfs(req, ino, reply)
+    }
+
+    fn getxattr(&self, req: &Request, ino: INodeNo, name: &OsStr, size: u32, reply: ReplyXattr) {
+        self.0.getxattr(req, ino, name, size, reply)
+    }
+
+    fn listxattr(&self, req: &Request, ino: INodeNo, size: u32, reply: ReplyXattr) {
+        self.0.listxattr(req, ino, size, reply)
+    }
+
+    fn setxattr(&self, req: &Request, ino: INodeNo, name: &OsStr, value: &[u8], flags: i32, position: u32, reply: ReplyEmpty) {
+        self.0.setxattr(req, ino, name, value, flags, position, reply)
+    }
+
+    fn removexattr(&self, req: &Request, ino: INodeNo, name: &OsStr, reply: ReplyEmpty) {
+        self.0.removexattr(req, ino, name, reply)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn relative_path_within_the_same_directory() {
+        assert_eq!(relative_path("+DATA/ORCL/DATAFILE", "+DATA/ORCL/DATAFILE/USERS.259.1"), "USERS.259.1");
+        assert_eq!(relative_path("+DATA/ORCL/DATAFILE/", "/+DATA/ORCL/DATAFILE/USERS.259.1"), "USERS.259.1");
+    }
+
+    #[test]
+    fn relative_path_to_a_sibling_directory() {
+        assert_eq!(relative_path("+DATA/ORCL/LINKS", "+DATA/ORCL/DATAFILE/USERS.259.1"), "../DATAFILE/USERS.259.1");
+        assert_eq!(relative_path("+DATA/ORCL/A/B", "+DATA/ORCL/DATAFILE/USERS.259.1"), "../../DATAFILE/USERS.259.1");
+        assert_eq!(relative_path("+DATA/ORCL", "+DATA/CDB/spfile.ora"), "../CDB/spfile.ora");
+    }
+
+    #[test]
+    fn relative_path_to_an_ancestor_directory() {
+        assert_eq!(relative_path("+DATA/ORCL/LINKS/OLD", "+DATA/ORCL/spfile.ora"), "../../spfile.ora");
+        assert_eq!(relative_path("+DATA/ORCL", "+DATA/spfile.ora"), "../spfile.ora");
+        assert_eq!(relative_path("+FRA/ORCL", "+DATA/ORCL/spfile.ora"), "../../+DATA/ORCL/spfile.ora");
+    }
+
+    #[test]
+    fn link_targets_below_the_root() {
+        assert_eq!(below_root("+DATA/ORCL", "+DATA/ORCL/DATAFILE/USERS.259.1"), Some("DATAFILE/USERS.259.1"));
+        assert_eq!(below_root("+DATA/ORCL", "+data/orcl/spfile.ora"), Some("spfile.ora"));
+        assert_eq!(below_root("+DATA/ORCL", "+DATA/ORCL2/spfile.ora"), None);
+        assert_eq!(below_root("+DATA/ORCL", "+DATA/CDB/spfile.ora"), None);
+        assert_eq!(below_root("+DATA/ORCL", "+DATA/ORCL"), None);
+        assert_eq!(below_root("+DATA/ORCL", "+DATA"), None);
+
+        // with -o relative_links the link's directory and target both lie below the root
+        assert_eq!(relative_path("+DATA/ORCL/LINKS", "+DATA/ORCL/DATAFILE/USERS.259.1"), "../DATAFILE/USERS.259.1");
+    }
+
+    // proc_read(): whole blocks from the start of the block `offset` falls in, short at the end of the file
+    fn proc_read(content: &[u8], offset: u64, bytes: u32, block_size: u64) -> Result<Vec<u8>, Error> {
+        let start = (offset / block_size * block_size) as usize;
+        let end = (start + bytes as usize).min(content.len());
+        Ok(content[start..end].to_vec())
+    }
+
+    #[test]
+    fn gap_read_matches_raw_read_at_4k() {
+        let block_size = 8192;
+        let content: Vec<u8> = (0..65536u32).map(|i| (i.wrapping_mul(2654435761) >> 24) as u8).collect();
+
+        let mut gap = vec![0u8; 8192];
+        read_whole_blocks(&mut gap, 4096, block_size, |offset, bytes| proc_read(&content, offset, bytes, block_size)).unwrap();
+        assert_eq!(gap, &content[4096..12288]);
+
+        let mut tail = vec![0u8; 100];
+        read_whole_blocks(&mut tail, 65436, block_size, |offset, bytes| proc_read(&content, offset, bytes, block_size)).unwrap();
+        assert_eq!(tail, &content[65436..]);
+
+        let mut past_end = vec![0u8; 8192];
+        assert!(read_whole_blocks(&mut past_end, 61440, block_size, |offset, bytes| proc_read(&content, offset, bytes, block_size)).is_err());
     }
 }
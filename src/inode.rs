@@ -14,6 +14,32 @@ pub struct Inode(u64);
 const ENTRY_ROOT: u32 = 0x000000; // 24-bit min value
 const ALIAS_FOR_DG: u32 = 0xffff_ffff;
 
+/*
+ * Synthetic entries (everything below /.asmfs/) are not backed by v$asm_alias. ASM never assigns
+ * group number 0 to a diskgroup, so group# 0 is free for them: entry_number selects the kind of
+ * synthetic entry and alias_index is a kind-specific payload. Root (ino 1) has entry_number 0 and
+ * is therefore not part of this range.
+ */
+const GROUP_SYNTHETIC: u8 = 0;
+pub const SYNTHETIC_CONTROL: u32 = 0x000001; // /.asmfs/ (payload 0) and its files (payload = file id)
+pub const SYNTHETIC_DISKS: u32 = 0x000002;   // /.asmfs/disks/ (payload 0) and its group directories (payload = group#)
+pub const SYNTHETIC_DISK: u32 = 0x000003;    // /.asmfs/disks/<group>/<disk#>/, payload = group# << 16 | disk#
+pub const SYNTHETIC_DISK_DEVICE: u32 = 0x000004;  // .../<disk#>/device, same payload
+pub const SYNTHETIC_DISK_HEADER: u32 = 0x000005;  // .../<disk#>/header.txt, same payload
+
+/*
+ * Virtual views inside a diskgroup (+DATA/.by-number/, ...) take entry numbers just below the top
+ * of the 24-bit range. ASM numbers directory entries upwards from 0 and gives every file alias the
+ * entry 0xFFFFFF, so this range is never reached. alias_index is VIEW_DIR for the view's own
+ * directory, otherwise a view-specific payload (e.g. the file number of a file that has no alias).
+ */
+const ENTRY_VIEW_MIN: u32 = 0xFF_FF00;
+const ENTRY_VIEW_MAX: u32 = 0xFF_FFFE;
+pub const ENTRY_VIEW_BY_NUMBER: u32 = 0xFF_FFFE;
+pub const ENTRY_VIEW_BY_TYPE: u32 = 0xFF_FFFD;      // payload: file type id
+pub const ENTRY_VIEW_BY_DATABASE: u32 = 0xFF_FFFC;  // payload: entry_number of the database directory << 8 | file type id
+pub const VIEW_DIR: u32 = 0xffff_ffff;
+
 impl Inode {
 
     pub fn from_ino(value: u64) -> Inode {
@@ -32,6 +58,14 @@ impl Inode {
         Inode::from_alias(reference_index, alias_index)
     }
 
+    pub fn from_synthetic(kind: u32, payload: u32) -> Inode {
+        Inode::from_alias(((GROUP_SYNTHETIC as u32) << 24) | (kind & 0x00FF_FFFF), payload)
+    }
+
+    pub fn from_view(group_number: u8, view: u32, payload: u32) -> Inode {
+        Inode::from_alias(((group_number as u32) << 24) | (view & 0x00FF_FFFF), payload)
+    }
+
     /*pub fn from_root() -> Inode {
         Inode(1)
     }*/
@@ -60,6 +94,27 @@ impl Inode {
         self.get_alias_index() == ALIAS_FOR_DG && self._get_entry_number() == ENTRY_ROOT
     }
 
+    pub fn is_synthetic(&self) -> bool {
+        self.get_group_number() == GROUP_SYNTHETIC && self._get_entry_number() != ENTRY_ROOT
+    }
+
+    pub fn is_view(&self) -> bool {
+        self.get_group_number() != GROUP_SYNTHETIC
+            && (ENTRY_VIEW_MIN..=ENTRY_VIEW_MAX).contains(&self._get_entry_number())
+    }
+
+    pub fn get_view(&self) -> u32 {
+        self._get_entry_number()
+    }
+
+    pub fn get_synthetic_kind(&self) -> u32 {
+        self._get_entry_number()
+    }
+
+    pub fn get_synthetic_payload(&self) -> u32 {
+        self.get_alias_index()
+    }
+
 
     pub fn _debug_dump(&self) {
         println!("Inode {} -> group_number: {}, reference_index={}/{:X}, alias_index={:X}, entry_number={:X}, is_diskgroup={}", self.0, self.get_group_number(),
@@ -0,0 +1,71 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use log::{LevelFilter, Log, Metadata, Record};
+
+/*
+ * env_logger configured by RUST_LOG, with a level that `asmfs ctl log-level` can override at
+ * runtime. While an override is set, records are filtered by that level alone (RUST_LOG module
+ * directives do not apply); `asmfs ctl log-level reset` goes back to RUST_LOG.
+ */
+
+static OVERRIDE: AtomicUsize = AtomicUsize::new(NO_OVERRIDE);
+const NO_OVERRIDE: usize = usize::MAX;
+
+struct RuntimeLogger {
+    from_env: env_logger::Logger,   // RUST_LOG
+    everything: env_logger::Logger, // same format, nothing filtered; used while an override is set
+}
+
+impl RuntimeLogger {
+    fn level_override() -> Option<LevelFilter> {
+        match OVERRIDE.load(Ordering::Relaxed) {
+            NO_OVERRIDE => None,
+            level => LevelFilter::iter().nth(level),
+        }
+    }
+}
+
+impl Log for RuntimeLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        match Self::level_override() {
+            Some(level) => metadata.level() <= level,
+            None => self.from_env.enabled(metadata),
+        }
+    }
+
+    fn log(&self, record: &Record) {
+        match Self::level_override() {
+            Some(level) if record.level() <= level => self.everything.log(record),
+            Some(_) => {}
+            None => self.from_env.log(record),
+        }
+    }
+
+    fn flush(&self) {
+        self.from_env.flush();
+    }
+}
+
+// replaces env_logger::init()
+pub fn init() {
+    let from_env = env_logger::Builder::from_default_env().build();
+    let everything = env_logger::Builder::new().filter_level(LevelFilter::Trace).build();
+    let max_level = from_env.filter();
+
+    if log::set_boxed_logger(Box::new(RuntimeLogger { from_env, everything })).is_ok() {
+        log::set_max_level(max_level);
+    }
+}
+
+// None goes back to RUST_LOG
+pub fn set_level(level: Option<LevelFilter>) {
+    match level {
+        Some(level) => {
+            OVERRIDE.store(level as usize, Ordering::Relaxed);
+            log::set_max_level(level);
+        }
+        None => {
+            OVERRIDE.store(NO_OVERRIDE, Ordering::Relaxed);
+            log::set_max_level(env_logger::Builder::from_default_env().build().filter());
+        }
+    }
+}
@@ -3,24 +3,53 @@ mod oracle;
 mod fuse;
 mod inode;
 mod afd;
+mod config;
+mod control;
+mod disks;
+mod stats;
+mod views;
+mod filter;
+mod permissions;
+mod policy;
+mod audit;
+mod throttle;
+mod logging;
+mod ctl;
+mod metrics;
+mod notify;
+mod wait;
+mod credentials;
+mod devices;
+mod privileges;
 
+use std::env;
+use std::ffi::OsString;
 use std::fs::{File, OpenOptions};
 use std::io::{Read, Write};
 use std::os::fd::{AsRawFd, FromRawFd};
-use std::path::Path;
-use clap::{Arg, ArgAction, Command};
+use std::path::{Path, PathBuf};
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use clap::parser::ValueSource;
 use fuser::MountOption;
 use fuser::SessionACL;
 use fuser::Config;
-use fuse::AsmFS;
+use std::sync::Arc;
+use fuse::{AsmFS, SharedAsmFS};
+use config::{load_config_file, MountConfig};
+use permissions::{parse_mode, DEFAULT_DIR_MODE, DEFAULT_FILE_MODE};
+use throttle::parse_rate;
 use crate::oraenv::bootstrap_oracle_env;
+use crate::wait::{parse_timeout, wait_until_ready, Deadline};
 
 fn main() {
-    bootstrap_oracle_env(
-        &std::env::args_os().collect::<Vec<_>>(),
-    );
+    // the client side of the control socket needs neither Oracle nor FUSE
+    let args: Vec<_> = std::env::args_os().collect();
+    if args.get(1).is_some_and(|arg| arg == "ctl") {
+        let args: Vec<String> = args[2..].iter().map(|arg| arg.to_string_lossy().into_owned()).collect();
+        std::process::exit(ctl::run_client(&args));
+    }
 
-    env_logger::init();
+    logging::init();
 
     let matches = Command::new(env!("CARGO_PKG_NAME"))
         .version(env!("CARGO_PKG_VERSION"))
@@ -46,6 +75,43 @@ fn main() {
                 .help("Connection string to remote ASM instance - user/pass@host:port/service (user must have sysdba)")
                 .action(ArgAction::Set),
         )
+        .arg(
+            Arg::new("conn-file")
+                .long("conn-file")
+                .value_name("PATH")
+                .help("Read the connection string from this file (mode 0600) instead of --conn")
+                .action(ArgAction::Set),
+        )
+        .arg(
+            Arg::new("failover-conn")
+                .long("failover-conn")
+                .value_name("TARGET")
+                .help("Another instance serving the same diskgroups, host:port/service or alias; may be repeated")
+                .action(ArgAction::Append),
+        )
+        .arg(
+            Arg::new("sid")
+                .long("sid")
+                .value_name("ORACLE_SID")
+                .help("Use this local ASM instance, e.g. +ASM2 (default: the +ASM instance with the lowest PID)")
+                .action(ArgAction::Set),
+        )
+        .arg(
+            Arg::new("oracle-home")
+                .long("oracle-home")
+                .value_name("PATH")
+                .help("Use the Oracle client libraries of this home (default: the home the ASM instance runs from)")
+                .action(ArgAction::Set),
+        )
+        .arg(
+            Arg::new("wait-for-asm")
+                .long("wait-for-asm")
+                .value_name("TIMEOUT")
+                .num_args(0..=1)
+                .require_equals(true)
+                .default_missing_value("")
+                .help("Wait until ASM is started and its diskgroups are mounted, at most TIMEOUT (e.g. 300, 10m)"),
+        )
         .arg(
             Arg::new("no-raw")
                 .long("no-raw")
@@ -89,6 +155,12 @@ fn main() {
                 .action(ArgAction::SetTrue)
                 .help("Automatically unmount on process exit"),
         )
+        .arg(
+            Arg::new("ignore-case")
+                .long("ignore-case")
+                .action(ArgAction::SetTrue)
+                .help("Look up names case-insensitively, like ASM does"),
+        )
         .arg(
             Arg::new("uid")
                 .long("uid")
@@ -107,6 +179,18 @@ fn main() {
                     "GID reported as the owner of all filesystem entries \
                         (default: effective GID of the asmfs process)"),
         )
+        .arg(
+            Arg::new("diskgroups")
+                .long("diskgroups")
+                .value_name("DG1,DG2")
+                .help("Expose only these diskgroups (default: all mounted diskgroups)"),
+        )
+        .arg(
+            Arg::new("root")
+                .long("root")
+                .value_name("+DG/DIR")
+                .help("Mount this ASM directory as the filesystem root (default: list of diskgroups)"),
+        )
         // mount(8) may pass following standard external-helper flags. Most are handled
         // upstream by mount or mount.fuse3; accepting them prevents clap from
         // rejecting valid helper invocations. In this interface, -f means fake
@@ -143,11 +227,18 @@ fn main() {
                 .action(ArgAction::Set)
                 .hide(true),
         )
+        .arg(
+            Arg::new("config")
+                .long("config")
+                .value_name("PATH")
+                .help("Read options from this TOML file (default: /etc/asmfs.toml, if it exists)")
+                .action(ArgAction::Set),
+        )
         .arg(
             Arg::new("fake")
                 .short('f')
                 .action(ArgAction::SetTrue)
-                .help("Validate arguments without mounting"),
+                .help("Validate arguments and print the resulting configuration without mounting"),
         )
         .get_matches();
 
@@ -159,20 +250,52 @@ fn main() {
               ("no-magic", None),
           ]
     */
-    let mount_options: Vec<(&str, Option<&str>)> = matches
+    let mut dash_o_options: Vec<(&str, Option<&str>)> = Vec::new();
+    for item in matches
         .get_many::<String>("mount-options")
         .into_iter()
         .flatten()
         .flat_map(|options| options.split(','))
         .filter(|item| !item.is_empty())
-        .map(|item| match item.split_once('=') {
+    {
+        let option = match item.split_once('=') {
             Some((key, value)) => (key, Some(value)),
             None => (item, None),
-        })
-        .collect();
+        };
+
+        // -o diskgroups=FRA,RECO: a bare name after diskgroups= is one more diskgroup, not an option
+        let continues_diskgroups = matches!(dash_o_options.last(), Some(("diskgroups", Some(_))))
+            && option.1.is_none()
+            && !is_known_mount_option(option.0);
+
+        if continues_diskgroups {
+            dash_o_options.push(("diskgroups", Some(item)));
+        } else {
+            dash_o_options.push(option);
+        }
+    }
+
+    for (key, _) in dash_o_options.iter().filter(|(key, _)| !is_known_mount_option(key)) {
+        eprintln!("asmfs: warning: unknown mount option '{key}' ignored");
+    }
 
 
     let mountpoint_arg = matches.get_many::<String>("PATH_ARGS").unwrap();
+
+    let config_file = match matches.get_one::<String>("config") {
+        Some(path) => Some(path.clone()),
+        None => mount_option_string(&dash_o_options, "config", None).unwrap_or_else(|e| {
+            eprintln!("{e}");
+            std::process::exit(2);
+        }),
+    };
+    let file_options = load_config_file(config_file.as_deref(), mountpoint_arg.clone().next_back().unwrap()).unwrap_or_else(|e| {
+        eprintln!("{e}");
+        std::process::exit(2);
+    });
+    let command_line_options = command_line_options(&matches);
+
+    let mount_options = merge_options(&file_options, &dash_o_options, &command_line_options);
     let daemon = matches.get_flag("daemon") || mount_option_present(&mount_options, "daemon") || mountpoint_arg.len() >= 2;
     let mountpoint_arg = mountpoint_arg.last().unwrap(); // intentionally, because first argument is "dummy" when fstab is used.
 
@@ -181,9 +304,154 @@ fn main() {
         eprintln!("{e}");
         std::process::exit(2);
     });
+    let conn_file = matches.get_one::<String>("conn-file");
+    let conn_file = mount_option_string(&mount_options, "conn_file", conn_file.cloned()).unwrap_or_else(|e| {
+        eprintln!("{e}");
+        std::process::exit(2);
+    });
+    let credentials = credentials::resolve(connection_string, conn_file).unwrap_or_else(|e| {
+        eprintln!("{e}");
+        std::process::exit(2);
+    });
+    let connection_string = credentials.connection_string;
+
+    // other RAC instances, tried in turn when the one of --conn fails; user and password are those of --conn
+    let failover_conns = mount_option_all(&mount_options, "failover_conn").unwrap_or_else(|e| {
+        eprintln!("{e}");
+        std::process::exit(2);
+    });
+    let failover_conns: Vec<String> = failover_conns
+        .into_iter()
+        .chain(matches.get_many::<String>("failover-conn").into_iter().flatten().map(String::as_str))
+        .map(str::to_string)
+        .collect();
+    if !failover_conns.is_empty() && connection_string.is_none() {
+        eprintln!("--failover-conn requires --conn or --conn-file");
+        std::process::exit(2);
+    }
+    if let Some(target) = failover_conns.iter().find(|target| target.contains('@')) {
+        eprintln!("Invalid --failover-conn {target}: give host:port/service or an alias, user and password come from --conn");
+        std::process::exit(2);
+    }
+
+    // the re-exec of bootstrap_oracle_env() gets a password from the command line through the environment
+    let args: Vec<OsString> = match &credentials.command_line_password {
+        Some(password) => {
+            credentials::scrub_cmdline(password);
+            // SAFETY: no other thread exists yet
+            unsafe { env::set_var(credentials::PASSWORD_ENV, password) };
+            args.iter().map(|arg| credentials::without_password(arg, password)).collect()
+        }
+        None => args,
+    };
+
+    // --wait-for-asm without a value and -o wait_for_asm without one wait forever
+    let wait_for_asm_option = mount_options
+        .iter()
+        .rev()
+        .find(|(key, _)| *key == "wait_for_asm")
+        .map(|(_, timeout)| timeout.unwrap_or("").to_string());
+    let wait_for_asm = wait_for_asm_option
+        .as_deref()
+        .map(parse_timeout)
+        .transpose()
+        .unwrap_or_else(|e| {
+            eprintln!("{e}");
+            std::process::exit(2);
+        })
+        .map(Deadline::new);
+
+    let sid = matches.get_one::<String>("sid");
+    let sid = mount_option_string(&mount_options, "sid", sid.cloned()).unwrap_or_else(|e| {
+        eprintln!("{e}");
+        std::process::exit(2);
+    });
+    let oracle_home = matches.get_one::<String>("oracle-home");
+    let oracle_home = mount_option_string(&mount_options, "oracle_home", oracle_home.cloned()).unwrap_or_else(|e| {
+        eprintln!("{e}");
+        std::process::exit(2);
+    });
+
+    // a remote ASM (--conn) has no asm_pmon process here to wait for, and -f does not wait at all
+    let wait_for_pmon = wait_for_asm.filter(|_| connection_string.is_none() && !matches.get_flag("fake"));
+    bootstrap_oracle_env(&args, sid.as_deref(), oracle_home.as_deref(), wait_for_pmon);
+
+    // not re-executed; nothing else needs the password in the environment
+    // SAFETY: no other thread exists yet
+    unsafe { env::remove_var(credentials::PASSWORD_ENV) };
 
     let use_raw = !matches.get_flag("no-raw") && !mount_option_present(&mount_options, "no-raw");
+    let hybrid = mount_option_present(&mount_options, "hybrid");
+    if hybrid && !use_raw {
+        eprintln!("-o hybrid fills the gaps of raw reads, it cannot be combined with --no-raw");
+        std::process::exit(2);
+    }
     let magic = !matches.get_flag("no-magic") && !mount_option_present(&mount_options, "no-magic");
+    let ignore_case = matches.get_flag("ignore-case") || mount_option_present(&mount_options, "ignore_case") || mount_option_present(&mount_options, "ignore-case");
+    let relative_links = mount_option_present(&mount_options, "relative_links");
+    let hard_links = mount_option_present(&mount_options, "hard_links");
+    let hide_omf = mount_option_present(&mount_options, "hide_omf");
+
+    let file_mode = mount_option_mode(&mount_options, "file_mode", DEFAULT_FILE_MODE).unwrap_or_else(|e| {
+        eprintln!("{e}");
+        std::process::exit(2);
+    });
+    let dir_mode = mount_option_mode(&mount_options, "dir_mode", DEFAULT_DIR_MODE).unwrap_or_else(|e| {
+        eprintln!("{e}");
+        std::process::exit(2);
+    });
+    let umask = mount_option_mode(&mount_options, "umask", 0).unwrap_or_else(|e| {
+        eprintln!("{e}");
+        std::process::exit(2);
+    });
+    let asm_acl = mount_option_present(&mount_options, "asm_acl");
+    let policy_file = mount_option_string(&mount_options, "policy_file", None).unwrap_or_else(|e| {
+        eprintln!("{e}");
+        std::process::exit(2);
+    });
+    let audit_log = mount_option_string(&mount_options, "audit_log", None).unwrap_or_else(|e| {
+        eprintln!("{e}");
+        std::process::exit(2);
+    });
+    let max_bps = mount_option_rate(&mount_options, "max_bps").unwrap_or_else(|e| {
+        eprintln!("{e}");
+        std::process::exit(2);
+    });
+    let max_iops = mount_option_int(&mount_options, "max_iops", 0u64).unwrap_or_else(|e| {
+        eprintln!("{e}");
+        std::process::exit(2);
+    });
+    let throttle_file = mount_option_string(&mount_options, "throttle_file", None).unwrap_or_else(|e| {
+        eprintln!("{e}");
+        std::process::exit(2);
+    });
+    let control_socket = mount_option_string(&mount_options, "control_socket", None).unwrap_or_else(|e| {
+        eprintln!("{e}");
+        std::process::exit(2);
+    });
+    let metrics = mount_option_string(&mount_options, "metrics", None).unwrap_or_else(|e| {
+        eprintln!("{e}");
+        std::process::exit(2);
+    });
+
+    // the user to become once the disks are open, see privileges.rs
+    let run_as = mount_option_string(&mount_options, "run_as", None).unwrap_or_else(|e| {
+        eprintln!("{e}");
+        std::process::exit(2);
+    });
+    let run_as_ids = run_as
+        .as_deref()
+        .map(|user| {
+            let (uid, gid) = privileges::lookup_user(user)?;
+            privileges::check_run_as(uid)?;
+            Ok::<_, String>((uid, gid))
+        })
+        .transpose()
+        .unwrap_or_else(|e| {
+            eprintln!("{e}");
+            std::process::exit(2);
+        });
+    let confine = mount_option_present(&mount_options, "confine");
     let mirror = matches.get_one::<String>("mirror").map(|s| s.as_str()).unwrap_or("0");
     let mirror: u8 = mirror.parse().unwrap_or(0);
     let mirror = mount_option_int(&mount_options, "mirror", mirror).unwrap_or_else(|e| {
@@ -197,8 +465,8 @@ fn main() {
         std::process::exit(2);
     });
 
-    let effective_uid = unsafe { libc::geteuid() } as u32;
-    let effective_gid = unsafe { libc::getegid() } as u32;
+    // with -o run_as=, the files belong to that user rather than to root
+    let (effective_uid, effective_gid) = run_as_ids.unwrap_or_else(|| unsafe { (libc::geteuid(), libc::getegid()) });
 
     let owner_uid = matches.get_one::<u32>("uid").copied().unwrap_or(effective_uid);
     let owner_uid = mount_option_int(&mount_options, "uid", owner_uid).unwrap_or_else(|e| {
@@ -218,11 +486,55 @@ fn main() {
         std::process::exit(2);
     });
 
+    let diskgroups_option = mount_option_all(&dash_o_options, "diskgroups").unwrap_or_else(|e| {
+        eprintln!("{e}");
+        std::process::exit(2);
+    });
+    let diskgroups_file: Vec<&str> = file_options
+        .iter()
+        .filter(|(key, _)| key == "diskgroups")
+        .filter_map(|(_, value)| value.as_deref())
+        .collect();
+    let diskgroups = select_diskgroups(matches.get_one::<String>("diskgroups").map(String::as_str), diskgroups_option, diskgroups_file);
+
+    // +DATA/ORCL/DATAFILE, with or without the leading '+' or surrounding slashes
+    let root = matches.get_one::<String>("root");
+    let root = mount_option_string(&mount_options, "root", root.cloned()).unwrap_or_else(|e| {
+        eprintln!("{e}");
+        std::process::exit(2);
+    });
+    let root = root
+        .map(|path| format!("+{}", path.trim().trim_matches('/').trim_start_matches('+')))
+        .filter(|path| path != "+");
+
+    let include = mount_option_all(&mount_options, "include").unwrap_or_else(|e| {
+        eprintln!("{e}");
+        std::process::exit(2);
+    });
+    let exclude = mount_option_all(&mount_options, "exclude").unwrap_or_else(|e| {
+        eprintln!("{e}");
+        std::process::exit(2);
+    });
+    let filter_file = mount_option_string(&mount_options, "filter_file", None).unwrap_or_else(|e| {
+        eprintln!("{e}");
+        std::process::exit(2);
+    });
+
     if mount_option_present(&mount_options, "rw") {
         eprintln!("asmfs is read-only; mount option 'rw' is not supported");
         std::process::exit(2);
     }
 
+    if relative_links && hard_links {
+        eprintln!("mount options 'relative_links' and 'hard_links' are mutually exclusive");
+        std::process::exit(2);
+    }
+
+    if hide_omf && hard_links {
+        eprintln!("mount options 'hide_omf' and 'hard_links' are mutually exclusive");
+        std::process::exit(2);
+    }
+
     if mirror > 2 {
         eprintln!("mirror must be 0, 1, or 2");
         std::process::exit(2);
@@ -241,12 +553,11 @@ fn main() {
         }
     };
 
-    if matches.get_flag("fake") {
-        return;
-    }
+    let fake = matches.get_flag("fake");
 
     let mountpoint = match std::fs::canonicalize(mountpoint_arg) {
         Ok(path) => path,
+        Err(_) if fake => PathBuf::from(mountpoint_arg),
         Err(e) => {
             eprintln!("Failed to resolve mountpoint '{}': {e}", mountpoint_arg);
             std::process::exit(1);
@@ -269,28 +580,127 @@ fn main() {
         options.push(MountOption::AutoUnmount);
     }
 
+    let auto_unmount = options.contains(&MountOption::AutoUnmount);
+
     options.push(MountOption::CUSTOM("max_read=33554432".into())); // 32MB max read
     options.push(MountOption::RO); // force read-only
     options.push(MountOption::Async);
 
+    // the ASM ACL mapping is only worth something when the kernel enforces it
+    if asm_acl || mount_option_present(&mount_options, "default_permissions") {
+        options.push(MountOption::DefaultPermissions);
+    }
+
     let mut cfg = Config::default();
     cfg.acl = acl;
     cfg.n_threads = Some(threads);
     cfg.clone_fd = true;
     cfg.mount_options = options;
 
+    let mount_config = MountConfig {
+        config_file: config_file.or_else(|| Path::new(config::DEFAULT_CONFIG_FILE).exists().then(|| config::DEFAULT_CONFIG_FILE.to_string())),
+        mount_point: mountpoint_string,
+        connection_string,
+        failover_conns,
+        use_raw,
+        hybrid,
+        magic,
+        mirror,
+        threads,
+        owner_uid,
+        owner_gid,
+        allow_root,
+        allow_other,
+        auto_unmount,
+        daemon,
+        log_file: log_file.clone(),
+        diskgroups,
+        root,
+        include: include.into_iter().map(str::to_string).collect(),
+        exclude: exclude.into_iter().map(str::to_string).collect(),
+        filter_file,
+        ignore_case,
+        relative_links,
+        hard_links,
+        hide_omf,
+        file_mode,
+        dir_mode,
+        umask,
+        asm_acl,
+        policy_file,
+        audit_log,
+        max_bps,
+        max_iops,
+        throttle_file,
+        control_socket: control_socket.clone(),
+        metrics: metrics.clone(),
+        sid,
+        oracle_home,
+        wait_for_asm: wait_for_asm_option,
+        run_as,
+        confine,
+    };
+
+    if fake {
+        print!("{}", mount_config.render());
+        return;
+    }
+
     let mut status_pipe = start_daemon(daemon);
 
-    let asmfs = match AsmFS::new(mountpoint_string, connection_string, use_raw, magic, mirror, owner_uid, owner_gid) {
+    let ready = wait_for_asm.map_or(Ok(()), |deadline| wait_until_ready(&mount_config, deadline));
+    if let Err(e) = ready {
+        startup_failed(&mut status_pipe, &e);
+    }
+
+    notify::status("Connecting to ASM");
+
+    let asmfs = match AsmFS::new(mount_config) {
         Ok(asmfs) => asmfs,
         Err(e) => startup_failed(&mut status_pipe, &e)
     };
 
-    let session = match fuser::Session::new(asmfs, &mountpoint, &cfg) {
+    let asmfs = Arc::new(asmfs);
+
+    // -o run_as=: the disks are opened while still root; mounting and everything after runs as that user
+    if let Some((uid, gid)) = run_as_ids {
+        let dropped = asmfs.open_device_pool().and_then(|_| privileges::run_as(uid, gid));
+        if let Err(e) = dropped {
+            startup_failed(&mut status_pipe, &e);
+        }
+    }
+
+    let session = match fuser::Session::new(SharedAsmFS(Arc::clone(&asmfs)), &mountpoint, &cfg) {
         Ok(session) => session,
         Err(e) => startup_failed(&mut status_pipe, &format!("Failed to mount FUSE filesystem: {e}"))
     };
 
+    // the failures below leave a mount behind unless the session is dropped first
+
+    // after mounting with the setuid fusermount3, before any thread that should be confined is started
+    let confined = if confine { privileges::confine(&asmfs.config) } else { Ok(()) };
+    if let Err(e) = confined {
+        drop(session);
+        startup_failed(&mut status_pipe, &e);
+    }
+
+    let control = control_socket.as_deref().map_or(Ok(()), |path| ctl::start_server(Arc::clone(&asmfs), path));
+    if let Err(e) = control {
+        drop(session);
+        startup_failed(&mut status_pipe, &e);
+    }
+
+    let metrics = metrics.as_deref().map_or(Ok(()), |address| metrics::start_server(Arc::clone(&asmfs), address));
+    if let Err(e) = metrics {
+        drop(session);
+        startup_failed(&mut status_pipe, &e);
+    }
+
+    if let Err(e) = notify::start_watchdog(Arc::clone(&asmfs)) {
+        drop(session);
+        startup_failed(&mut status_pipe, &e);
+    }
+
     let background = match session.spawn() {
         Ok(background) => background,
         Err(e) => startup_failed(&mut status_pipe, &format!("Failed to start FUSE workers: {e}"))
@@ -313,13 +723,98 @@ fn main() {
         drop(pipe);
     }
 
-    if let Err(e) = background.join() {
+    notify::notify(&format!("READY=1\nSTATUS=Serving {}", mountpoint.display()));
+
+    let result = background.join();
+    notify::notify("STOPPING=1");
+
+    if let Err(e) = result {
         eprintln!("FUSE session failed: {e}");
         std::process::exit(1);
     }
 
 }
 
+// option names of the command line flags that take a value, e.g. --oracle-home => oracle_home
+const COMMAND_LINE_OPTIONS: &[(&str, &str)] = &[
+    ("conn", "conn"),
+    ("conn-file", "conn_file"),
+    ("sid", "sid"),
+    ("oracle-home", "oracle_home"),
+    ("wait-for-asm", "wait_for_asm"), // "" without a value
+    ("mirror", "mirror"),
+    ("threads", "threads"),
+    ("uid", "uid"),
+    ("gid", "gid"),
+    ("log-file", "log-file"),
+    ("root", "root"),
+];
+
+// accepted by mount(8) and the fuse mount helpers in fstab; asmfs has no use for them
+const GENERIC_MOUNT_OPTIONS: &[&str] = &[
+    "ro", "rw", "defaults", "auto", "noauto", "user", "users", "nouser", "nofail", "_netdev",
+    "dev", "nodev", "suid", "nosuid", "exec", "noexec", "atime", "noatime", "relatime", "async", "sync",
+];
+
+fn is_known_mount_option(key: &str) -> bool {
+    key == "config"
+        || key.starts_with("x-")
+        || config::CONFIG_FILE_KEYS.contains(&key)
+        || GENERIC_MOUNT_OPTIONS.contains(&key)
+}
+
+/*
+ Options from the config file, then -o, then command line values; where an option is given
+ more than once, mount_option_*() use the last one, so the command line wins over -o and -o
+ over the file. Flags and lists (include, exclude, failover_conn) add up instead.
+*/
+fn merge_options<'a>(
+    file_options: &'a [(String, Option<String>)],
+    dash_o_options: &[(&'a str, Option<&'a str>)],
+    command_line_options: &'a [(&'static str, String)],
+) -> Vec<(&'a str, Option<&'a str>)> {
+    file_options
+        .iter()
+        .map(|(key, value)| (key.as_str(), value.as_deref()))
+        .chain(dash_o_options.iter().copied())
+        .chain(command_line_options.iter().map(|(key, value)| (*key, Some(value.as_str()))))
+        .collect()
+}
+
+/*
+ Like an option with a single value, the source with the highest precedence that names
+ diskgroups wins: --diskgroups over -o over the config file. Within one source the names add
+ up; -o takes them as diskgroups=FRA:RECO, diskgroups=FRA,RECO or a repeated diskgroups=.
+*/
+fn select_diskgroups(command_line: Option<&str>, dash_o: Vec<&str>, file: Vec<&str>) -> Option<Vec<String>> {
+    let lists = match command_line {
+        Some(list) => vec![list],
+        None if !dash_o.is_empty() => dash_o,
+        None => file,
+    };
+
+    let diskgroups: Vec<String> = lists
+        .iter()
+        .flat_map(|list| list.split([',', ':']))
+        .map(|name| name.trim().trim_start_matches('+').to_uppercase())
+        .filter(|name| !name.is_empty())
+        .collect();
+
+    if diskgroups.is_empty() { None } else { Some(diskgroups) }
+}
+
+// values given on the command line (not clap defaults), as mount options
+fn command_line_options(matches: &ArgMatches) -> Vec<(&'static str, String)> {
+    COMMAND_LINE_OPTIONS
+        .iter()
+        .filter(|(id, _)| matches.value_source(id) == Some(ValueSource::CommandLine))
+        .filter_map(|(id, key)| {
+            let value = matches.get_raw(id)?.next_back()?;
+            Some((*key, value.to_string_lossy().into_owned()))
+        })
+        .collect()
+}
+
 fn mount_option_present(
     options: &[(&str, Option<&str>)],
     name: &str,
@@ -361,6 +856,46 @@ fn mount_option_string(
     }
 }
 
+fn mount_option_rate(
+    options: &[(&str, Option<&str>)],
+    name: &str,
+) -> Result<u64, String> {
+    match options.iter().rev().find(|(key, _)| *key == name) {
+        None => Ok(0),
+        Some((_, None)) => {
+            Err(format!("mount option '{name}' requires a value"))
+        }
+        Some((_, Some(value))) => parse_rate(value)
+            .map_err(|e| format!("invalid value for mount option '{name}': {e}")),
+    }
+}
+
+fn mount_option_mode(
+    options: &[(&str, Option<&str>)],
+    name: &str,
+    fallback: u32,
+) -> Result<u32, String> {
+    match options.iter().rev().find(|(key, _)| *key == name) {
+        None => Ok(fallback),
+        Some((_, None)) => {
+            Err(format!("mount option '{name}' requires a value"))
+        }
+        Some((_, Some(value))) => parse_mode(value)
+            .map_err(|e| format!("invalid value for mount option '{name}': {e}")),
+    }
+}
+
+fn mount_option_all<'a>(
+    options: &[(&str, Option<&'a str>)],
+    name: &str,
+) -> Result<Vec<&'a str>, String> {
+    options
+        .iter()
+        .filter(|(key, _)| *key == name)
+        .map(|(_, value)| value.ok_or_else(|| format!("mount option '{name}' requires a value")))
+        .collect()
+}
+
 /*
 --daemon needs two processes:
   - The parent waits only long enough to learn whether mounting succeeded, then exits.
@@ -489,3 +1024,63 @@ fn redirect_daemon_stdio(log_file: Option<&Path>) -> std::io::Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(options: &[(&str, Option<&str>)]) -> Vec<(String, Option<String>)> {
+        options.iter().map(|(key, value)| (key.to_string(), value.map(str::to_string))).collect()
+    }
+
+    #[test]
+    fn command_line_wins_over_dash_o_over_file() {
+        let file_options = file(&[("root", Some("+FRA")), ("mirror", Some("1")), ("uid", Some("54321"))]);
+        let dash_o_options = [("root", Some("+DATA")), ("mirror", Some("2"))];
+        let command_line_options = [("root", "+RECO".to_string())];
+
+        let options = merge_options(&file_options, &dash_o_options, &command_line_options);
+
+        assert_eq!(mount_option_string(&options, "root", None), Ok(Some("+RECO".to_string())));
+        assert_eq!(mount_option_int(&options, "mirror", 0u8), Ok(2));
+        assert_eq!(mount_option_int(&options, "uid", 0u32), Ok(54321));
+        assert_eq!(mount_option_int(&options, "threads", 4u32), Ok(4));
+        assert_eq!(mount_option_string(&options, "sid", Some("+ASM1".to_string())), Ok(Some("+ASM1".to_string())));
+    }
+
+    #[test]
+    fn lists_and_flags_add_up() {
+        let file_options = file(&[("exclude", Some("*.trc")), ("allow_other", None)]);
+        let dash_o_options = [("exclude", Some("*/ONLINELOG/*")), ("include", Some("+FRA/*")), ("hide_omf", None)];
+
+        let options = merge_options(&file_options, &dash_o_options, &[]);
+
+        assert_eq!(mount_option_all(&options, "exclude"), Ok(vec!["*.trc", "*/ONLINELOG/*"]));
+        assert_eq!(mount_option_all(&options, "include"), Ok(vec!["+FRA/*"]));
+        assert_eq!(mount_option_all(&options, "failover_conn"), Ok(vec![]));
+        assert!(mount_option_present(&options, "allow_other"));
+        assert!(mount_option_present(&options, "hide_omf"));
+        assert!(!mount_option_present(&options, "daemon"));
+    }
+
+    #[test]
+    fn options_without_a_required_value_are_errors() {
+        let options = [("root", None), ("mirror", None), ("exclude", Some("*.trc")), ("exclude", None)];
+
+        assert!(mount_option_string(&options, "root", None).is_err());
+        assert!(mount_option_int(&options, "mirror", 0u8).is_err());
+        assert!(mount_option_all(&options, "exclude").is_err());
+        assert!(mount_option_int(&[("mirror", Some("x"))], "mirror", 0u8).is_err());
+    }
+
+    #[test]
+    fn diskgroups_come_from_one_source() {
+        let names = |names: &[&str]| Some(names.iter().map(|name| name.to_string()).collect::<Vec<_>>());
+
+        assert_eq!(select_diskgroups(Some("+fra,RECO"), vec!["DATA"], vec!["ARCH"]), names(&["FRA", "RECO"]));
+        assert_eq!(select_diskgroups(None, vec!["FRA:reco", "DATA"], vec!["ARCH"]), names(&["FRA", "RECO", "DATA"]));
+        assert_eq!(select_diskgroups(None, vec![], vec!["ARCH", "+DATA"]), names(&["ARCH", "DATA"]));
+        assert_eq!(select_diskgroups(None, vec![], vec![]), None);
+        assert_eq!(select_diskgroups(Some(" , "), vec![], vec!["ARCH"]), None);
+    }
+}
@@ -0,0 +1,176 @@
+use std::fmt::Write as _;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::sync::Arc;
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::Duration;
+use log::{error, warn};
+use crate::ctl::bind_private_socket;
+use crate::fuse::AsmFS;
+use crate::stats::{errno_name, Latency, Op, LATENCY_BUCKETS_US, SQL_ERRORS, SQL_LATENCY};
+
+/*
+ * Prometheus endpoint (-o metrics=ADDRESS), serving the text exposition format over plain
+ * HTTP/1.1 at /metrics:
+ *
+ *   -o metrics=127.0.0.1:9469              TCP
+ *   -o metrics=unix:/run/asmfs/metrics.sock  Unix domain socket (mode 0600 like the control socket)
+ *
+ * One request per connection, answered in turn by a single thread; scrapes are rare and cheap,
+ * everything below is read from counters that already exist. Disks and diskgroups are labelled
+ * by number, as in v$asm_disk.
+ */
+
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub fn start_server(asmfs: Arc<AsmFS>, address: &str) -> Result<(), String> {
+    let serve_all: Box<dyn FnOnce() + Send> = match address.strip_prefix("unix:") {
+        Some(path) => {
+            let listener = bind_private_socket(path, "metrics")?;
+
+            Box::new(move || {
+                for stream in listener.incoming() {
+                    match stream {
+                        Ok(stream) => {
+                            let _ = stream.set_read_timeout(Some(READ_TIMEOUT));
+                            serve(&asmfs, stream);
+                        }
+                        Err(e) => error!("metrics socket: {}", e),
+                    }
+                }
+            })
+        }
+        None => {
+            let listener = TcpListener::bind(address)
+                .map_err(|e| format!("Unable to listen for metrics on {address}: {e}"))?;
+
+            Box::new(move || {
+                for stream in listener.incoming() {
+                    match stream {
+                        Ok(stream) => {
+                            let _ = stream.set_read_timeout(Some(READ_TIMEOUT));
+                            serve(&asmfs, stream);
+                        }
+                        Err(e) => error!("metrics listener: {}", e),
+                    }
+                }
+            })
+        }
+    };
+
+    thread::Builder::new()
+        .name("asmfs-metrics".to_string())
+        .spawn(serve_all)
+        .map_err(|e| format!("Unable to start metrics thread: {e}"))?;
+
+    Ok(())
+}
+
+fn serve<S: Read + Write>(asmfs: &AsmFS, stream: S) {
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+
+    if reader.by_ref().take(8192).read_line(&mut request_line).is_err() {
+        return;
+    }
+
+    // the headers are of no interest, but have to be read before answering
+    let mut header = String::new();
+    while reader.by_ref().take(8192).read_line(&mut header).is_ok_and(|len| len > 0) && !header.trim().is_empty() {
+        header.clear();
+    }
+
+    let (status, body) = match request_line.split_whitespace().nth(1) {
+        Some("/metrics") => ("200 OK", asmfs.render_metrics()),
+        _ => ("404 Not Found", "asmfs serves /metrics only\n".to_string()),
+    };
+
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+
+    if let Err(e) = reader.into_inner().write_all(response.as_bytes()) {
+        warn!("metrics: unable to answer: {}", e);
+    }
+}
+
+impl AsmFS {
+    pub(crate) fn render_metrics(&self) -> String {
+        let mut out = String::new();
+
+        metric_header(&mut out, "asmfs_uptime_seconds", "gauge", "Seconds since the filesystem was mounted.");
+        let _ = writeln!(out, "asmfs_uptime_seconds {}", self.stats.started.elapsed().as_secs());
+
+        metric_header(&mut out, "asmfs_operation_duration_seconds", "histogram", "Latency of FUSE operations.");
+        for op in Op::ALL {
+            render_histogram(&mut out, "asmfs_operation_duration_seconds", &format!("op=\"{}\"", op.name()), &self.stats.ops[op as usize]);
+        }
+
+        metric_header(&mut out, "asmfs_sql_duration_seconds", "histogram", "Latency of SQL statements, on all connections.");
+        render_histogram(&mut out, "asmfs_sql_duration_seconds", "", &SQL_LATENCY);
+
+        metric_header(&mut out, "asmfs_sql_errors_total", "counter", "SQL statements that failed.");
+        let _ = writeln!(out, "asmfs_sql_errors_total {}", SQL_ERRORS.load(Ordering::Relaxed));
+
+        metric_header(&mut out, "asmfs_read_bytes_total", "counter", "Bytes read from ASM disks in raw mode.");
+        for ((group_number, disk_number), bytes) in self.stats.bytes_read_disk.read().unwrap().iter() {
+            let _ = writeln!(out, "asmfs_read_bytes_total{{group=\"{}\",disk=\"{}\"}} {}", group_number, disk_number, bytes.load(Ordering::Relaxed));
+        }
+
+        metric_header(&mut out, "asmfs_gap_read_bytes_total", "counter", "Bytes of raw-mode files read through DBMS_DISKGROUP because the ASM disk is not accessible (-o hybrid).");
+        for ((group_number, disk_number), bytes) in self.stats.bytes_read_gap.read().unwrap().iter() {
+            let _ = writeln!(out, "asmfs_gap_read_bytes_total{{group=\"{}\",disk=\"{}\"}} {}", group_number, disk_number, bytes.load(Ordering::Relaxed));
+        }
+
+        metric_header(&mut out, "asmfs_dbms_read_bytes_total", "counter", "Bytes read through DBMS_DISKGROUP (--no-raw).");
+        let _ = writeln!(out, "asmfs_dbms_read_bytes_total {}", self.stats.bytes_read_dbms.load(Ordering::Relaxed));
+
+        metric_header(&mut out, "asmfs_open_handles", "gauge", "Currently open file handles.");
+        let _ = writeln!(out, "asmfs_open_handles{{mode=\"raw\"}} {}", self.handles_raw.read().unwrap().len());
+        let _ = writeln!(out, "asmfs_open_handles{{mode=\"dbms\"}} {}", self.handles_dbms.lock().unwrap().len());
+        let _ = writeln!(out, "asmfs_open_handles{{mode=\"gap\"}} {}", self.handles_gap.lock().unwrap().len());
+        let _ = writeln!(out, "asmfs_open_handles{{mode=\"control\"}} {}", self.handles_control.lock().unwrap().len());
+
+        metric_header(&mut out, "asmfs_cache_hits_total", "counter", "Lookups answered from a cache.");
+        let _ = writeln!(out, "asmfs_cache_hits_total{{cache=\"asm_user_group\"}} {}", self.id_cache.hits.load(Ordering::Relaxed));
+        metric_header(&mut out, "asmfs_cache_misses_total", "counter", "Lookups a cache could not answer.");
+        let _ = writeln!(out, "asmfs_cache_misses_total{{cache=\"asm_user_group\"}} {}", self.id_cache.misses.load(Ordering::Relaxed));
+
+        metric_header(&mut out, "asmfs_reconnects_total", "counter", "Connections replaced by a failover, `asmfs ctl reconnect` or the systemd watchdog.");
+        let _ = writeln!(out, "asmfs_reconnects_total {}", self.stats.reconnects.load(Ordering::Relaxed));
+
+        metric_header(&mut out, "asmfs_errors_total", "counter", "Error replies to the kernel, by errno.");
+        for (code, count) in self.stats.errors.read().unwrap().iter() {
+            let _ = writeln!(out, "asmfs_errors_total{{errno=\"{}\"}} {}", errno_name(*code), count.load(Ordering::Relaxed));
+        }
+
+        out
+    }
+}
+
+fn metric_header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+// Prometheus buckets are cumulative, Latency buckets are not
+fn render_histogram(out: &mut String, name: &str, labels: &str, latency: &Latency) {
+    let separator = if labels.is_empty() { "" } else { "," };
+    let mut cumulative = 0;
+
+    for (bound_us, bucket) in LATENCY_BUCKETS_US.iter().zip(latency.buckets.iter()) {
+        cumulative += bucket.load(Ordering::Relaxed);
+        let _ = writeln!(out, "{}_bucket{{{}{}le=\"{}\"}} {}", name, labels, separator, *bound_us as f64 / 1_000_000.0, cumulative);
+    }
+
+    let count = latency.count.load(Ordering::Relaxed);
+    let _ = writeln!(out, "{}_bucket{{{}{}le=\"+Inf\"}} {}", name, labels, separator, count);
+
+    let braced = if labels.is_empty() { String::new() } else { format!("{{{}}}", labels) };
+    let _ = writeln!(out, "{}_sum{} {}", name, braced, latency.total_us.load(Ordering::Relaxed) as f64 / 1_000_000.0);
+    let _ = writeln!(out, "{}_count{} {}", name, braced, count);
+}
@@ -0,0 +1,137 @@
+use std::env;
+use std::os::linux::net::SocketAddrExt;
+use std::os::unix::net::{SocketAddr, UnixDatagram};
+use std::sync::{Arc, TryLockError};
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::{Duration, Instant};
+use log::{error, info, warn};
+use crate::fuse::{connect_metadata, AsmFS};
+
+/*
+ * systemd service notifications (sd_notify(3)) for units with Type=notify, see pkg/asmfs@.service.
+ * Without NOTIFY_SOCKET in the environment, i.e. when not started by systemd, nothing is sent.
+ *
+ * READY=1 follows a successful mount, STATUS= tells `systemctl status` what asmfs is doing, and
+ * with WatchdogSec= set, WATCHDOG=1 is sent only while the metadata connection answers a ping.
+ * A ping that fails is followed by one reconnect attempt, to the next instance of --failover-conn
+ * if there is one; if that fails too, the pings stop and systemd restarts the mount once
+ * WatchdogSec= runs out.
+ */
+
+// sends `state`, e.g. "READY=1\nSTATUS=...", to the service manager; false if it could not be sent
+pub fn notify(state: &str) -> bool {
+    let Some(path) = env::var_os("NOTIFY_SOCKET") else {
+        return false;
+    };
+
+    let path = path.to_string_lossy();
+
+    // '@' is an abstract socket
+    let address = match path.strip_prefix('@') {
+        Some(name) => SocketAddr::from_abstract_name(name.as_bytes()),
+        None => SocketAddr::from_pathname(path.as_ref()),
+    };
+
+    let sent = address.and_then(|address| UnixDatagram::unbound()?.send_to_addr(state.as_bytes(), &address));
+
+    if let Err(e) = &sent {
+        warn!("Unable to notify systemd on {}: {}", path, e);
+    }
+
+    sent.is_ok()
+}
+
+pub fn status(text: &str) {
+    notify(&format!("STATUS={}", text));
+}
+
+// WATCHDOG_USEC, if the watchdog is enabled for this process
+fn watchdog_interval() -> Option<Duration> {
+    let usec: u64 = env::var("WATCHDOG_USEC").ok()?.parse().ok()?;
+
+    // set for another process, e.g. inherited from a parent that forked
+    let for_us = env::var("WATCHDOG_PID")
+        .ok()
+        .and_then(|pid| pid.parse::<u32>().ok())
+        .is_none_or(|pid| pid == std::process::id());
+
+    Some(Duration::from_micros(usec)).filter(|interval| for_us && !interval.is_zero())
+}
+
+pub fn start_watchdog(asmfs: Arc<AsmFS>) -> Result<(), String> {
+    let Some(interval) = watchdog_interval() else {
+        return Ok(());
+    };
+
+    info!("systemd watchdog every {:?}", interval / 2);
+
+    thread::Builder::new()
+        .name("asmfs-watchdog".to_string())
+        .spawn(move || {
+            let mut busy_since: Option<Instant> = None;
+            let mut healthy = true;
+
+            loop {
+                thread::sleep(interval / 2);
+
+                let checked = asmfs.check_oracle(&mut busy_since, interval);
+
+                match &checked {
+                    Ok(()) => {
+                        notify("WATCHDOG=1");
+                    }
+                    Err(e) => error!("watchdog: {}", e),
+                }
+
+                // STATUS= only when the state changes
+                match (healthy, &checked) {
+                    (false, Ok(())) => status("Connected to ASM"),
+                    (true, Err(e)) => status(&format!("ASM connection lost: {e}")),
+                    _ => {}
+                }
+
+                healthy = checked.is_ok();
+            }
+        })
+        .map_err(|e| format!("Unable to start watchdog thread: {e}"))?;
+
+    Ok(())
+}
+
+impl AsmFS {
+    /*
+     * A connection that is busy with a query counts as healthy, unless the same query has been
+     * running for a whole watchdog interval: a hung ASM instance should not be hidden behind it.
+     */
+    fn check_oracle(&self, busy_since: &mut Option<Instant>, interval: Duration) -> Result<(), String> {
+        let (ping, target) = match self.ora.try_lock() {
+            Ok(ora) => (ora.ping(), ora.target()),
+            Err(TryLockError::WouldBlock) => {
+                let since = *busy_since.get_or_insert_with(Instant::now);
+                return if since.elapsed() < interval {
+                    Ok(())
+                } else {
+                    Err(format!("metadata connection busy for {} s", since.elapsed().as_secs()))
+                };
+            }
+            Err(TryLockError::Poisoned(_)) => return Err("metadata connection is poisoned".to_string()),
+        };
+
+        *busy_since = None;
+
+        let Err(e) = ping else {
+            return Ok(());
+        };
+
+        warn!("watchdog: ping failed, reconnecting: {}", e);
+
+        // with --failover-conn, to the next instance
+        let ora = connect_metadata(&self.config, target + 1).map_err(|e| format!("reconnect failed: {e}"))?;
+        info!("watchdog: reconnected to {}", ora.target_name());
+        *self.ora.lock().unwrap() = ora;
+        self.stats.reconnects.fetch_add(1, Ordering::Relaxed);
+
+        Ok(())
+    }
+}
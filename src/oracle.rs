@@ -1,23 +1,38 @@
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
-use oracle::{Connection, Connector, Error, ErrorKind, Privilege, Row, ResultSet};
+use oracle::{ConnStatus, Connection, Connector, Error, ErrorKind, Privilege, Row, ResultSet, Statement};
+use oracle::sql_type::ToSql;
 use fuser::{FileType, FileAttr, INodeNo};
 use oracle::sql_type::{OracleType, Timestamp};
 use chrono::{NaiveDate, DateTime, Utc};
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::fs::File;
 
 use crate::inode;
-use inode::Inode;
+use inode::{Inode, ENTRY_VIEW_BY_NUMBER};
 use crate::afd::get_afd_map;
+use crate::devices;
+use crate::stats::{SQL_ERRORS, SQL_LATENCY};
 use log::{debug, error, warn, info}; // debug, info, error
 
 
 //use log::{info}; // debug, error
 
 pub struct OracleConnection {
-    conn: Connection
+    conn: Connection,
+    target: usize,                   // which instance of --conn and --failover-conn, see connect()
+    target_name: String,
+    lost: AtomicBool,                // a statement failed because the session or its instance is gone
+    diskgroups: Option<Vec<String>>, // when set, only these diskgroups (names without '+') are visible
+    ignore_case: bool,               // lookups by name compare upper case, like ASM itself
+    hide_omf: bool,                  // system-created file names are hidden when the file has a user alias
 }
 
+// the session or instance is gone: killed, not logged on, shutdown, end-of-file on communication
+// channel, not connected, lost contact, and TNS errors of a listener or instance that went away
+const CONNECTION_LOST_ORA: &[i32] = &[28, 1012, 1033, 1034, 1089, 1092, 3113, 3114, 3135, 12514, 12528, 12537, 12541, 12547, 12570, 12571];
+const CONNECTION_LOST_DPI: &[i32] = &[1010, 1080]; // not connected, closed by ORA-
+
 const ASM_ALIAS_COLUMNS: &str = "a.reference_index, a.alias_index, a.file_number, a.name, a.alias_directory, a.system_created, a.parent_index";
 const ASM_FILE_COLUMNS: &str = "f.bytes, f.blocks, f.creation_date, f.modification_date, f.type, f.striped";
 
@@ -35,16 +50,50 @@ pub const MAGIC_FILE_TYPES: &[(&str, u32, u32, u32, u32)] = &[  // file_type, ma
 // ARCHIVELOG in 26ai needs no fix.
 
 pub struct RawOpenFileHandle {
+    pub(crate) ino: u64,                 // what was opened; a flush re-opens the handle from it
     pub(crate) au_list: Vec<(u16, u32)>, // disk_number, allocation_unit
     pub(crate) au_size: u32,
     pub(crate) file_size_bytes: u64,
     pub(crate) file_type: String, // as seen in v$asm_file.type
-    pub(crate) disk_list: HashMap<u16, File>, // disk_number => open file handle of (e.g. /dev/sdc)
+    pub(crate) disk_list: HashMap<u16, File>, // disk_number => open file handle of (e.g. /dev/sdc); with -o hybrid not every disk
     pub(crate) file_number: u32, // this is for debugging purposes
+    pub(crate) group_number: u8,
     pub(crate) striped: u8,       // v$asm_file.striped => const ASM_STRIPED_COARSE, ASM_STRIPED_FINE,
     pub(crate) fine_stripe_count: u32, // only computed when striped = ASM_STRIPED_FINE
 }
 
+pub struct AsmDiskgroup {
+    pub group_number: u8,       // v$asm_diskgroup.group_number
+    pub name: String,           // v$asm_diskgroup.name (with leading '+')
+    pub au_size: u32,           // v$asm_diskgroup.allocation_unit_size
+    pub sector_size: u32,       // v$asm_diskgroup.sector_size
+    pub redundancy: String,     // v$asm_diskgroup.type (EXTERN, NORMAL, HIGH, ...)
+    pub state: String,          // v$asm_diskgroup.state
+}
+
+pub struct AsmDiskgroupSpace {
+    pub group_number: u8,       // v$asm_diskgroup.group_number
+    pub au_size: u32,           // v$asm_diskgroup.allocation_unit_size
+    pub total_mb: u64,          // v$asm_diskgroup.total_mb
+    pub free_mb: u64,           // v$asm_diskgroup.free_mb
+    pub usable_file_mb: u64,    // v$asm_diskgroup.usable_file_mb (negative values are reported as 0)
+    pub files: u64,             // count(*) of v$asm_file in this group
+}
+
+pub struct AsmDisk {
+    pub disk_number: u16,       // v$asm_disk.disk_number
+    pub name: String,           // v$asm_disk.name
+    pub failgroup: String,      // v$asm_disk.failgroup
+    pub path: String,           // v$asm_disk.path, with AFD labels resolved to block devices
+    pub os_mb: u64,             // v$asm_disk.os_mb
+}
+
+pub struct AsmFileAcl {
+    pub owner: Option<String>,          // v$asm_user.os_name
+    pub usergroup: Option<String>,      // v$asm_usergroup.name
+    pub permissions: Option<String>,    // v$asm_file.permissions, e.g. "rw-r-----"
+}
+
 struct AsmAlias {
     reference_index: u32,                   // v$asm_alias.reference_index (contains group_number in high-order 8 bits), use get_inode.get_group_number
     alias_index: u32,                       // v$asm_alias.alias_index
@@ -95,6 +144,27 @@ impl AsmAlias {
         })
     }
 
+    // a file that is only reachable through .by-number/, because none of its aliases survived
+    pub fn from_row_orphan(row: &Row, group_number: u8) -> Result<Self, Error> {
+        let file_number: u32 = row.get("FILE_NUMBER")?;
+        let incarnation: u32 = row.get("INCARNATION")?;
+        let inode = Inode::from_view(group_number, ENTRY_VIEW_BY_NUMBER, file_number);
+
+        Ok(Self {
+            reference_index: inode.get_reference_index(),
+            alias_index: inode.get_alias_index(),
+            parent_index: inode.get_reference_index(),
+            file_number,
+            name: format!("{}.{}", file_number, incarnation),
+            alias_directory: "N".to_string(),
+            system_created: "Y".to_string(),
+            bytes: row.get("BYTES")?,
+            blocks: row.get("BLOCKS")?,
+            creation_date: row.get("CREATION_DATE")?,
+            modification_date: row.get("MODIFICATION_DATE")?
+        })
+    }
+
     pub fn get_inode(&self) -> Inode {
         Inode::from_alias(self.reference_index, self.alias_index)
     }
@@ -151,14 +221,20 @@ impl AsmAlias {
 
 impl OracleConnection {
 
-    pub fn connect(conn_str: Option<String>) -> Result<Self, Error> {
+    /*
+     * Connects to the first instance that accepts the connection: the target of `conn_str`, then
+     * each of `failover` (--failover-conn) in turn, starting with number `first` and wrapping
+     * around. Failing over to another instance of the cluster is connect(.., target() + 1).
+     * A local bequeath connection (no conn_str) has no other instance to go to.
+     */
+    pub fn connect(conn_str: Option<String>, failover: &[String], first: usize) -> Result<Self, Error> {
         if conn_str.is_none() {
             let conn = Connector::new("", "", "")
                 .external_auth(true)
                 .privilege(Privilege::Sysasm)
                 .connect()?;
 
-            return Ok(Self{conn});
+            return Ok(Self::new(conn, 0, "local".to_string()));
         } else {
             let str = conn_str.unwrap();
 
@@ -178,11 +254,137 @@ impl OracleConnection {
                 }
             };
 
-            let conn = Connector::new(user, pass, inst)
-                .privilege(Privilege::Sysdba)
-                .connect()?;
+            let targets: Vec<&str> = std::iter::once(inst).chain(failover.iter().map(String::as_str)).collect();
+            let mut last_error = None;
+
+            for target in (0..targets.len()).map(|n| (first + n) % targets.len()) {
+                // "/@alias": user and password come from an Oracle Wallet (secure external password store)
+                let connected = Connector::new(user, pass, targets[target])
+                    .external_auth(user.is_empty() && pass.is_empty())
+                    .privilege(Privilege::Sysdba)
+                    .connect();
+
+                match connected {
+                    Ok(conn) => return Ok(Self::new(conn, target, targets[target].to_string())),
+                    Err(e) => {
+                        if targets.len() > 1 {
+                            warn!("Unable to connect to {}: {}", targets[target], e);
+                        }
+                        last_error = Some(e);
+                    }
+                }
+            }
+
+            return Err(last_error.expect("there is at least one target"));
+        }
+    }
+
+    fn new(conn: Connection, target: usize, target_name: String) -> Self {
+        Self{conn, target, target_name, lost: AtomicBool::new(false), diskgroups: None, ignore_case: false, hide_omf: false}
+    }
+
+    pub fn target(&self) -> usize {
+        self.target
+    }
+
+    // host:port/service or alias of the instance connected to
+    pub fn target_name(&self) -> &str {
+        &self.target_name
+    }
+
+    // true once a statement failed because the connection is dead; it has to be replaced
+    pub fn is_lost(&self) -> bool {
+        self.lost.load(Ordering::Relaxed)
+    }
+
+    fn note_error(&self, e: &Error) {
+        SQL_ERRORS.fetch_add(1, Ordering::Relaxed);
 
-            return Ok(Self{conn});
+        let lost = e.oci_code().is_some_and(|code| CONNECTION_LOST_ORA.contains(&code))
+            || e.dpi_code().is_some_and(|code| CONNECTION_LOST_DPI.contains(&code))
+            || !matches!(self.conn.status(), Ok(ConnStatus::Normal));
+
+        if lost && !self.lost.swap(true, Ordering::Relaxed) {
+            warn!("Connection to {} lost: {}", self.target_name, e);
+        }
+    }
+
+    // round trip without a statement, for the systemd watchdog
+    pub fn ping(&self) -> Result<(), Error> {
+        self.conn.ping()
+    }
+
+    // every statement goes through query(), query_row() or execute(), so SQL_LATENCY and note_error() see all of them
+    fn query(&self, sql: &str, params: &[&dyn ToSql]) -> Result<ResultSet<'_, Row>, Error> {
+        let _timer = SQL_LATENCY.time();
+        self.conn.query(sql, params).inspect_err(|e| self.note_error(e))
+    }
+
+    fn query_row(&self, sql: &str, params: &[&dyn ToSql]) -> Result<Row, Error> {
+        let _timer = SQL_LATENCY.time();
+        self.conn.query_row(sql, params).inspect_err(|e| self.note_error(e))
+    }
+
+    fn execute(&self, stmt: &mut Statement, params: &[&dyn ToSql]) -> Result<(), Error> {
+        let _timer = SQL_LATENCY.time();
+        stmt.execute(params).inspect_err(|e| self.note_error(e))
+    }
+
+    pub fn restrict_diskgroups(&mut self, diskgroups: Vec<String>) {
+        self.diskgroups = Some(diskgroups);
+    }
+
+    pub fn set_ignore_case(&mut self, ignore_case: bool) {
+        self.ignore_case = ignore_case;
+    }
+
+    // `column = value`, or both sides upper-cased with -o ignore_case
+    fn name_equals(&self, column: &str, value: &str) -> String {
+        if self.ignore_case {
+            format!("upper({column}) = upper({value})")
+        } else {
+            format!("{column} = {value}")
+        }
+    }
+
+    pub fn set_hide_omf(&mut self, hide_omf: bool) {
+        self.hide_omf = hide_omf;
+    }
+
+    // extra condition on v$asm_alias a, leaving out system-created file names that also have a user alias
+    fn omf_condition(&self) -> &'static str {
+        if !self.hide_omf {
+            return "";
+        }
+
+        r#"and not (a.system_created = 'Y' and a.alias_directory = 'N' and exists (
+                select 1 from v$asm_alias u
+                    where u.group_number = a.group_number and u.file_number = a.file_number
+                        and u.system_created = 'N' and u.alias_directory = 'N'))"#
+    }
+
+    fn diskgroup_allowed(&self, name: &str) -> bool {
+        let name = name.trim_start_matches('+');
+        match &self.diskgroups {
+            None => true,
+            Some(allowed) => allowed.iter().any(|dg| dg.eq_ignore_ascii_case(name)),
+        }
+    }
+
+    fn diskgroup_not_allowed(name: &str) -> Error {
+        Error::new(ErrorKind::Other, format!("asmfs; diskgroup {} is excluded by the 'diskgroups' mount option", name))
+    }
+
+    pub fn check_diskgroup_number(&self, group_number: u8) -> Result<(), Error> {
+        if self.diskgroups.is_none() {
+            return Ok(());
+        }
+
+        let name = self.query_asm_diskgroup_name(group_number)?;
+        if self.diskgroup_allowed(&name) {
+            Ok(())
+        } else {
+            Err(Self::diskgroup_not_allowed(&name))
         }
     }
 
@@ -193,30 +395,56 @@ impl OracleConnection {
                 FROM v$instance
         "#;  // 19030 for 19.30.0.0
              // 23026 for 23.26.1.0.0
-        self.conn.query_row(query, &[])
+        self.query_row(query, &[])
+    }
+
+    fn select_instance_status(&self) -> Result<Row, Error> {
+        let query = r#"
+            select status from v$instance
+        "#;
+        self.query_row(query, &[])
     }
 
     fn select_diskgroup_all(&self) -> Result<ResultSet<'_,Row>, Error> {
         let query = r#"
             select group_number, '+' || name as name from v$asm_diskgroup order by name
         "#;
-        self.conn.query(query, &[])
+        self.query(query, &[])
     }
 
-    fn select_diskgroup_by_name(&self, group_name: &str) -> Result<Row, Error> {
+    fn select_diskgroup_details(&self) -> Result<ResultSet<'_,Row>, Error> {
         let query = r#"
-            select group_number, '+' || name as name from v$asm_diskgroup where name=:1
+            select group_number, '+' || name as name, allocation_unit_size, sector_size, type, state
+                from v$asm_diskgroup
+                order by name
         "#;
-        self.conn.query_row(query, &[&group_name])
+        self.query(query, &[])
+    }
+
+    fn select_diskgroup_space(&self) -> Result<ResultSet<'_,Row>, Error> {
+        let query = r#"
+            select g.group_number, g.name, g.allocation_unit_size, g.total_mb, g.free_mb, g.usable_file_mb,
+                   (select count(*) from v$asm_file f where f.group_number = g.group_number) as files
+                from v$asm_diskgroup g
+                where g.group_number != 0
+        "#;
+        self.query(query, &[])
+    }
+
+    fn select_diskgroup_by_name(&self, group_name: &str) -> Result<Row, Error> {
+        let query = format!(r#"
+            select group_number, '+' || name as name from v$asm_diskgroup where {}
+        "#, self.name_equals("name", ":1"));
+        self.query_row(query.as_str(), &[&group_name])
     }
 
     fn select_diskgroup_by_number(&self, group_number: u8) -> Result<Row, Error> {
         let query = r#"
-          select group_number
+          select group_number, '+' || name as name
           from v$asm_diskgroup
           where group_number = :1
         "#;
-        self.conn.query_row(query, &[&group_number])
+        self.query_row(query, &[&group_number])
     }
 
     fn select_alias_by_parent_index(&self, parent_index: u32) -> Result<ResultSet<'_,Row>, Error> {
@@ -224,10 +452,11 @@ impl OracleConnection {
             select {}
                 from v$asm_alias a
                 where a.parent_index=:1
+                    {}
                 order by a.name
-        "#, ASM_ALIAS_COLUMNS);
+        "#, ASM_ALIAS_COLUMNS, self.omf_condition());
 
-        self.conn.query(query.as_str(), &[&parent_index])
+        self.query(query.as_str(), &[&parent_index])
     }
 
     fn select_alias_file_by_parent_index_and_name(&self, parent_index: u32, name: &str) -> Result<Row, Error> {
@@ -236,10 +465,11 @@ impl OracleConnection {
                 from v$asm_alias a
                 left join v$asm_file f on f.file_number = a.file_number and f.group_number = a.group_number
                 where a.parent_index = :1
-                    and a.name = :2
-        "#, ASM_ALIAS_COLUMNS, ASM_FILE_COLUMNS);
+                    and {}
+                    {}
+        "#, ASM_ALIAS_COLUMNS, ASM_FILE_COLUMNS, self.name_equals("a.name", ":2"), self.omf_condition());
 
-        self.conn.query_row(query.as_str(), &[&parent_index, &name])
+        self.query_row(query.as_str(), &[&parent_index, &name])
     }
 
     pub fn select_alias_file_by_reference_index_and_alias_index(&self, reference_index: u32, alias_index: u32) -> Result<Row, Error> {
@@ -250,7 +480,7 @@ impl OracleConnection {
                 where a.reference_index = :1 and a.alias_index = :2
         "#, ASM_ALIAS_COLUMNS, ASM_FILE_COLUMNS);
 
-        self.conn.query_row(query.as_str(), &[&reference_index, &alias_index])
+        self.query_row(query.as_str(), &[&reference_index, &alias_index])
     }
 
     fn select_alias_directory_by_reference_index(&self, reference_index: u32) -> Result<Row, Error> {
@@ -262,7 +492,159 @@ impl OracleConnection {
                 and a.alias_directory = 'Y'
           "#, ASM_ALIAS_COLUMNS);
 
-        self.conn.query_row(query.as_str(), &[&reference_index])
+        self.query_row(query.as_str(), &[&reference_index])
+    }
+
+    // the system-created alias of the file an alias points to, and the number of aliases of that file
+    fn select_system_alias_of_alias(&self, reference_index: u32, alias_index: u32) -> Result<Row, Error> {
+        let query = format!(r#"
+            select {}, {},
+                   (select count(*) from v$asm_alias c
+                        where c.group_number = a.group_number and c.file_number = a.file_number and c.alias_directory = 'N') as alias_count
+                from v$asm_alias u
+                join v$asm_alias a on a.group_number = u.group_number and a.file_number = u.file_number
+                left join v$asm_file f on f.file_number = a.file_number and f.group_number = a.group_number
+                where u.reference_index = :1 and u.alias_index = :2
+                    and a.system_created = 'Y'
+                    and a.alias_directory = 'N'
+        "#, ASM_ALIAS_COLUMNS, ASM_FILE_COLUMNS);
+
+        self.query_row(query.as_str(), &[&reference_index, &alias_index])
+    }
+
+    // ASM File Access Control of one file; owner and usergroup are null when not set
+    fn select_file_acl(&self, group_number: u8, file_number: u32) -> Result<Row, Error> {
+        let query = r#"
+            select u.os_name as owner, g.name as usergroup, f.permissions
+                from v$asm_file f
+                left join v$asm_user u on u.group_number = f.group_number
+                    and u.user_number = f.user_number and u.incarnation = f.user_incarnation
+                left join v$asm_usergroup g on g.group_number = f.group_number
+                    and g.usergroup_number = f.usergroup_number and g.incarnation = f.usergroup_incarnation
+                where f.group_number = :1 and f.file_number = :2
+        "#;
+
+        self.query_row(query, &[&group_number, &file_number])
+    }
+
+    // NAME/NAME/... from the directory below the diskgroup down to this alias
+    fn select_alias_path(&self, reference_index: u32, alias_index: u32) -> Result<Row, Error> {
+        let query = r#"
+            select listagg(a.name, '/') within group (order by level desc) as path
+                from v$asm_alias a
+                start with a.reference_index = :1 and a.alias_index = :2
+                connect by a.reference_index = prior a.parent_index and a.alias_directory = 'Y'
+        "#;
+
+        self.query_row(query, &[&reference_index, &alias_index])
+    }
+
+    // every file of a group, with its system-created alias (if it still has one)
+    fn select_file_by_group(&self, group_number: u8) -> Result<ResultSet<'_,Row>, Error> {
+        let query = r#"
+            select f.file_number, f.incarnation, a.reference_index, a.alias_index
+                from v$asm_file f
+                left join v$asm_alias a on a.group_number = f.group_number and a.file_number = f.file_number
+                    and a.system_created = 'Y' and a.alias_directory = 'N'
+                where f.group_number = :1
+                order by f.file_number
+        "#;
+
+        self.query(query, &[&group_number])
+    }
+
+    fn select_file_by_number(&self, group_number: u8, file_number: u32) -> Result<Row, Error> {
+        let query = format!(r#"
+            select f.file_number, f.incarnation, a.reference_index, a.alias_index, {}
+                from v$asm_file f
+                left join v$asm_alias a on a.group_number = f.group_number and a.file_number = f.file_number
+                    and a.system_created = 'Y' and a.alias_directory = 'N'
+                where f.group_number = :1
+                    and f.file_number = :2
+        "#, ASM_FILE_COLUMNS);
+
+        self.query_row(query.as_str(), &[&group_number, &file_number])
+    }
+
+    // v$asm_file columns of a file, either by its alias or (for files without one) by its number
+    fn select_file_by_ino(&self, ino: u64) -> Result<Row, Error> {
+        let inode = Inode::from_ino(ino);
+
+        if inode.is_view() && inode.get_view() == ENTRY_VIEW_BY_NUMBER {
+            self.select_file_by_number(inode.get_group_number(), inode.get_alias_index())
+        } else {
+            self.select_alias_file_by_reference_index_and_alias_index(inode.get_reference_index(), inode.get_alias_index())
+        }
+    }
+
+    // distinct v$asm_file.type of a group, or only of the files below one top-level (database) directory
+    fn select_file_types(&self, group_number: u8, db_reference_index: Option<u32>) -> Result<ResultSet<'_,Row>, Error> {
+        match db_reference_index {
+            None => {
+                let query = r#"
+                    select distinct f.type
+                        from v$asm_file f
+                        where f.group_number = :1
+                        order by f.type
+                "#;
+                self.query(query, &[&group_number])
+            }
+            Some(db_reference_index) => {
+                let query = r#"
+                    select distinct f.type
+                        from (
+                            select a.group_number, a.file_number, a.system_created, a.alias_directory
+                                from v$asm_alias a
+                                start with a.parent_index = :1
+                                connect by prior a.reference_index = a.parent_index
+                        ) a
+                        join v$asm_file f on f.group_number = a.group_number and f.file_number = a.file_number
+                        where a.system_created = 'Y'
+                            and a.alias_directory = 'N'
+                        order by f.type
+                "#;
+                self.query(query, &[&db_reference_index])
+            }
+        }
+    }
+
+    // system-created aliases of one file type; `name` narrows the result down to a single alias
+    fn select_system_alias_by_type(&self, group_number: u8, file_type: &str, db_reference_index: Option<u32>, name: Option<&str>) -> Result<ResultSet<'_,Row>, Error> {
+        match db_reference_index {
+            None => {
+                let query = format!(r#"
+                    select {}
+                        from v$asm_alias a
+                        join v$asm_file f on f.group_number = a.group_number and f.file_number = a.file_number
+                        where a.group_number = :1
+                            and f.type = :2
+                            and a.system_created = 'Y'
+                            and a.alias_directory = 'N'
+                            and {}
+                        order by a.name
+                "#, ASM_ALIAS_COLUMNS, self.name_equals("a.name", "nvl(:3, a.name)"));
+                self.query(query.as_str(), &[&group_number, &file_type, &name])
+            }
+            Some(db_reference_index) => {
+                let query = format!(r#"
+                    select {}
+                        from (
+                            select x.*
+                                from v$asm_alias x
+                                start with x.parent_index = :1
+                                connect by prior x.reference_index = x.parent_index
+                        ) a
+                        join v$asm_file f on f.group_number = a.group_number and f.file_number = a.file_number
+                        where a.group_number = :2
+                            and f.type = :3
+                            and a.system_created = 'Y'
+                            and a.alias_directory = 'N'
+                            and {}
+                        order by a.name
+                "#, ASM_ALIAS_COLUMNS, self.name_equals("a.name", "nvl(:4, a.name)"));
+                self.query(query.as_str(), &[&db_reference_index, &group_number, &file_type, &name])
+            }
+        }
     }
 
     fn select_extent_map(&self, group_number: u8, file_number: u32, mirror: u8) -> Result<ResultSet<'_,Row>, Error> {
@@ -281,7 +663,7 @@ impl OracleConnection {
 
         // println!("Running query [{}]", query);
         // println!("group_number={}, file_number={}, mirror={}", group_number, file_number, mirror);
-        self.conn.query(query, &[&group_number, &file_number, &mirror])
+        self.query(query, &[&group_number, &file_number, &mirror])
     }
 
     fn select_fine_stripe_width(&self) -> Result<Row, Error> {
@@ -292,7 +674,7 @@ impl OracleConnection {
                 WHERE a.ksppinm = '_asm_stripesize'
         "#;
 
-        self.conn.query_row(query, &[])
+        self.query_row(query, &[])
     }
 
     fn select_au_size(&self, group_number: u8) -> Result<Row, Error> {
@@ -300,7 +682,7 @@ impl OracleConnection {
             select allocation_unit_size from v$asm_diskgroup where group_number = :1
         "#;
 
-        self.conn.query_row(query, &[&group_number])
+        self.query_row(query, &[&group_number])
     }
 
     fn select_asm_disks(&self, group_number: u8) -> Result<ResultSet<'_,Row>, Error> {
@@ -308,7 +690,18 @@ impl OracleConnection {
             select disk_number, path from v$asm_disk where group_number = :1
         "#;
 
-        self.conn.query(query, &[&group_number])
+        self.query(query, &[&group_number])
+    }
+
+    fn select_asm_disk_details(&self, group_number: u8) -> Result<ResultSet<'_,Row>, Error> {
+        let query = r#"
+            select disk_number, name, failgroup, path, os_mb
+                from v$asm_disk
+                where group_number = :1
+                order by disk_number
+        "#;
+
+        self.query(query, &[&group_number])
     }
 
     pub fn query_fine_stripe_count(&self, _group_number: u8, _file_number: u32, _mirror: u8) -> Result<u32, Error> {
@@ -359,18 +752,46 @@ impl OracleConnection {
         for r in rs {
             let row = r?;
             let disk_number :u16 = row.get(0)?;
-            let mut path :String = row.get(1)?;
+            let path :String = row.get(1)?;
 
-            if path.starts_with("AFD:") {
-                path = path.replace("AFD:", "");
-                path = get_afd_map().get(&path).expect("Expected 'afdtool -getdevlist' to have value for all AFD disks in v$asm_disk").clone();
-            }
+            retval.insert(disk_number, resolve_disk_path(path));
+        }
+        Ok(retval)
+    }
 
-            retval.insert(disk_number, path);
+    pub fn query_asm_disk_details(&self, group_number: u8) -> Result<Vec<AsmDisk>, Error> {
+        self.check_diskgroup_number(group_number)?;
+        let rs = self.select_asm_disk_details(group_number)?;
+        let mut retval :Vec<AsmDisk> = Vec::new();
+        for r in rs {
+            let row = r?;
+            let name: Option<String> = row.get("NAME")?;
+            let failgroup: Option<String> = row.get("FAILGROUP")?;
+            let path: String = row.get("PATH")?;
+
+            retval.push(AsmDisk {
+                disk_number: row.get("DISK_NUMBER")?,
+                name: name.unwrap_or_default(),
+                failgroup: failgroup.unwrap_or_default(),
+                path: resolve_disk_path(path),
+                os_mb: row.get("OS_MB")?,
+            });
         }
         Ok(retval)
     }
 
+    pub fn query_asm_disk(&self, group_number: u8, disk_number: u16) -> Result<AsmDisk, Error> {
+        self.query_asm_disk_details(group_number)?
+            .into_iter()
+            .find(|disk| disk.disk_number == disk_number)
+            .ok_or_else(|| Error::new(ErrorKind::Other, format!("asmfs; no disk {} in group {}", disk_number, group_number)))
+    }
+
+    pub fn query_asm_diskgroup_name(&self, group_number: u8) -> Result<String, Error> {
+        let row = self.select_diskgroup_by_number(group_number)?;
+        row.get("NAME")
+    }
+
     pub fn query_extent_map(&self, group_number: u8, file_number: u32, mirror: u8) -> Result<Vec<(u16, u32)>, Error> {
         let rs = self.select_extent_map(group_number, file_number, mirror)?;
         let mut retval :Vec<(u16, u32)> = Vec::new();
@@ -401,6 +822,10 @@ impl OracleConnection {
             let group_number: u8 = row.get(0)?;
             let name: String = row.get(1)?;
 
+            if !self.diskgroup_allowed(&name) {
+                continue;
+            }
+
             let inode = Inode::from_group_number(group_number);
             // inode.debug_dump();
             retval.push((inode.get_ino(), FileType::Directory, name));
@@ -408,6 +833,61 @@ impl OracleConnection {
         Ok(retval)
     }
 
+    pub fn query_asm_diskgroup_details(&self) -> Result<Vec<AsmDiskgroup>, Error> {
+        let rs = self.select_diskgroup_details()?;
+        let mut retval :Vec<AsmDiskgroup> = Vec::new();
+        for r in rs {
+            let row = r?;
+            let name: String = row.get("NAME")?;
+
+            if !self.diskgroup_allowed(&name) {
+                continue;
+            }
+
+            retval.push(AsmDiskgroup {
+                group_number: row.get("GROUP_NUMBER")?,
+                name,
+                au_size: row.get("ALLOCATION_UNIT_SIZE")?,
+                sector_size: row.get("SECTOR_SIZE")?,
+                redundancy: row.get("TYPE")?,
+                state: row.get("STATE")?,
+            });
+        }
+        Ok(retval)
+    }
+
+    // mounted diskgroups only; dismounted groups are listed in v$asm_diskgroup with group_number 0
+    pub fn query_asm_diskgroup_space(&self) -> Result<Vec<AsmDiskgroupSpace>, Error> {
+        let rs = self.select_diskgroup_space()?;
+        let mut retval :Vec<AsmDiskgroupSpace> = Vec::new();
+        for r in rs {
+            let row = r?;
+            let name: String = row.get("NAME")?;
+
+            if !self.diskgroup_allowed(&name) {
+                continue;
+            }
+
+            let usable_file_mb: i64 = row.get("USABLE_FILE_MB")?;
+            retval.push(AsmDiskgroupSpace {
+                group_number: row.get("GROUP_NUMBER")?,
+                au_size: row.get("ALLOCATION_UNIT_SIZE")?,
+                total_mb: row.get("TOTAL_MB")?,
+                free_mb: row.get("FREE_MB")?,
+                usable_file_mb: usable_file_mb.max(0) as u64,
+                files: row.get("FILES")?,
+            });
+        }
+        Ok(retval)
+    }
+
+    // STARTED once an ASM instance is up
+    pub fn query_instance_status(&self) -> Result<String, Error> {
+        let row = self.select_instance_status()?;
+        let status: String = row.get(0)?;
+        Ok(status)
+    }
+
     pub fn query_oracle_version(&self) -> Result<u32, Error> {
         let row = self.select_oracle_version()?;
         let major_version: String = row.get(0)?;
@@ -416,6 +896,11 @@ impl OracleConnection {
 
     pub fn query_asm_diskgroup_ent_name(&self, name: &str) -> Result<FileAttr, Error> {
         let dg_name = name.replace("+", "");
+
+        if !self.diskgroup_allowed(&dg_name) {
+            return Err(Self::diskgroup_not_allowed(&dg_name));
+        }
+
         let row = self.select_diskgroup_by_name(dg_name.as_str())?;
 
         let group_number: u8 = row.get(0)?;
@@ -445,7 +930,13 @@ impl OracleConnection {
         let inode = Inode::from_ino(ino);
 
         // Query success proves that the encoded group is currently mounted.
-        self.select_diskgroup_by_number(inode.get_group_number())?;
+        let row = self.select_diskgroup_by_number(inode.get_group_number())?;
+        let name: String = row.get("NAME")?;
+
+        if !self.diskgroup_allowed(&name) {
+            return Err(Self::diskgroup_not_allowed(&name));
+        }
+
         let time = synthetic_dir_time();
 
         Ok(FileAttr {
@@ -467,6 +958,87 @@ impl OracleConnection {
         })
     }
 
+    // .by-number/ listing: "<file_number>.<incarnation>" pointing at the system alias,
+    // or at a by-number inode when the file has lost its aliases
+    pub fn query_by_number_vec(&self, group_number: u8) -> Result<Vec<(u64, FileType, String)>, Error> {
+        let rs = self.select_file_by_group(group_number)?;
+        let mut retval :Vec<(u64, FileType, String)> = Vec::new();
+        for r in rs {
+            let row = r?;
+            let file_number: u32 = row.get("FILE_NUMBER")?;
+            let incarnation: u32 = row.get("INCARNATION")?;
+            let reference_index: Option<u32> = row.get("REFERENCE_INDEX")?;
+            let alias_index: Option<u32> = row.get("ALIAS_INDEX")?;
+
+            let inode = match (reference_index, alias_index) {
+                (Some(reference_index), Some(alias_index)) => Inode::from_alias(reference_index, alias_index),
+                _ => Inode::from_view(group_number, ENTRY_VIEW_BY_NUMBER, file_number),
+            };
+
+            retval.push((inode.get_ino(), FileType::RegularFile, format!("{}.{}", file_number, incarnation)));
+        }
+        Ok(retval)
+    }
+
+    pub fn query_by_number_ent(&self, group_number: u8, name: &str) -> Result<FileAttr, Error> {
+        let parsed = name
+            .split_once('.')
+            .and_then(|(file_number, incarnation)| Some((file_number.parse::<u32>().ok()?, incarnation.parse::<u32>().ok()?)));
+
+        let Some((file_number, incarnation)) = parsed else {
+            return Err(Error::new(ErrorKind::Other, format!("asmfs; '{}' is not <file_number>.<incarnation>", name)));
+        };
+
+        let row = self.select_file_by_number(group_number, file_number)?;
+        let actual_incarnation: u32 = row.get("INCARNATION")?;
+
+        if actual_incarnation != incarnation {
+            return Err(Error::new(ErrorKind::Other, format!("asmfs; file {} in group {} has incarnation {}, not {}", file_number, group_number, actual_incarnation, incarnation)));
+        }
+
+        let reference_index: Option<u32> = row.get("REFERENCE_INDEX")?;
+        let alias_index: Option<u32> = row.get("ALIAS_INDEX")?;
+
+        match (reference_index, alias_index) {
+            (Some(reference_index), Some(alias_index)) => self.query_asm_alias_ent_ino(Inode::from_alias(reference_index, alias_index).get_ino()),
+            _ => Ok(AsmAlias::from_row_orphan(&row, group_number)?.get_file_attr()),
+        }
+    }
+
+    pub fn query_by_number_ent_ino(&self, ino: u64) -> Result<FileAttr, Error> {
+        let inode = Inode::from_ino(ino);
+        let row = self.select_file_by_number(inode.get_group_number(), inode.get_alias_index())?;
+        Ok(AsmAlias::from_row_orphan(&row, inode.get_group_number())?.get_file_attr())
+    }
+
+    pub fn query_file_types(&self, group_number: u8, db_reference_index: Option<u32>) -> Result<Vec<String>, Error> {
+        let rs = self.select_file_types(group_number, db_reference_index)?;
+        let mut retval :Vec<String> = Vec::new();
+        for r in rs {
+            let row = r?;
+            retval.push(row.get(0)?);
+        }
+        Ok(retval)
+    }
+
+    pub fn query_system_alias_by_type_vec(&self, group_number: u8, file_type: &str, db_reference_index: Option<u32>, name: Option<&str>) -> Result<Vec<(u64, FileType, String)>, Error> {
+        let rs = self.select_system_alias_by_type(group_number, file_type, db_reference_index, name)?;
+        let mut retval :Vec<(u64, FileType, String)> = Vec::new();
+        for r in rs {
+            let row = r?;
+            let alias = AsmAlias::from_row_alias(&row)?;
+            retval.push((alias.get_inode().get_ino(), alias.get_ftype(), alias.name));
+        }
+        Ok(retval)
+    }
+
+    // name of a directory alias, which also proves that it (still) exists
+    pub fn query_asm_directory_name(&self, reference_index: u32) -> Result<String, Error> {
+        let row = self.select_alias_directory_by_reference_index(reference_index)?;
+        let alias = AsmAlias::from_row_alias(&row)?;
+        Ok(alias.name)
+    }
+
     // all aliases in a given folder
     pub fn query_asm_alias_vec(&self, ino: u64) -> Result<Vec<(u64, FileType, String)>, Error> {
         let inode = Inode::from_ino(ino);
@@ -519,6 +1091,42 @@ impl OracleConnection {
         Ok(parent_alias.get_inode().get_ino())
     }
 
+    // -o hard_links: any alias of a file resolves to the file's system alias, with nlink = number of aliases
+    pub fn query_hard_link_ent_ino(&self, ino: u64) -> Result<FileAttr, Error> {
+        let inode = Inode::from_ino(ino);
+        let row = self.select_system_alias_of_alias(inode.get_reference_index(), inode.get_alias_index())?;
+        let alias = AsmAlias::from_row_file(&row)?;
+        let alias_count: u32 = row.get("ALIAS_COUNT")?;
+
+        let mut attr = alias.get_file_attr();
+        attr.nlink = alias_count.max(1);
+        Ok(attr)
+    }
+
+    pub fn query_file_acl(&self, ino: u64) -> Result<AsmFileAcl, Error> {
+        let inode = Inode::from_ino(ino);
+        let file_number: u32 = self.select_file_by_ino(ino)?.get("FILE_NUMBER")?;
+        let row = self.select_file_acl(inode.get_group_number(), file_number)?;
+
+        Ok(AsmFileAcl {
+            owner: row.get("OWNER")?,
+            usergroup: row.get("USERGROUP")?,
+            permissions: row.get("PERMISSIONS")?,
+        })
+    }
+
+    // +DATA/ORCL/DATAFILE/USERS.259.1 of a directory or file alias
+    pub fn query_asm_alias_path(&self, ino: u64) -> Result<String, Error> {
+        let inode = Inode::from_ino(ino);
+        let group_name = self.query_asm_diskgroup_name(inode.get_group_number())?;
+        let row = self.select_alias_path(inode.get_reference_index(), inode.get_alias_index())?;
+
+        match row.get::<_, Option<String>>("PATH")? {
+            Some(path) => Ok(format!("{}/{}", group_name, path)),
+            None => Err(Error::new(ErrorKind::Other, format!("asmfs; no alias for inode {}", ino))),
+        }
+    }
+
     pub fn query_asm_alias_link(&self, ino: u64) -> Result<String, Error> {
         let link_inode = Inode::from_ino(ino);
         let link_row = self.select_alias_file_by_reference_index_and_alias_index(link_inode.get_reference_index(), link_inode.get_alias_index())?;
@@ -540,7 +1148,7 @@ impl OracleConnection {
             fetch first 1 rows only
         "#;
 
-        let target_row = self.conn.query_row(query, &[&link_inode.get_group_number(), &link_struct.file_number])?;
+        let target_row = self.query_row(query, &[&link_inode.get_group_number(), &link_struct.file_number])?;
         let target_name :String = target_row.get("NAME")?;
         Ok(target_name)
     }
@@ -548,7 +1156,7 @@ impl OracleConnection {
     // filetype, filesize_ora, filesize_fs, blksize
     pub fn proc_getfilettr(&self, target_path: &String) -> Result<(u32, u64, u64, u32), Error> {
         let mut stmt = self.conn.statement("begin dbms_diskgroup.getfileattr(:b_target, :b_filetype, :b_filesize, :b_blksize); end;").build()?;
-        stmt.execute(&[target_path, &OracleType::Int64, &OracleType::Int64, &OracleType::Int64])?;
+        self.execute(&mut stmt, &[target_path, &OracleType::Int64, &OracleType::Int64, &OracleType::Int64])?;
         let filetype: u32 = stmt.bind_value(2)?;
         let filesize: u64 = stmt.bind_value(3)?;
         let blksize: u32 = stmt.bind_value(4)?; // logical block size
@@ -557,13 +1165,29 @@ impl OracleConnection {
         Ok((filetype, filesize, filesize, blksize))
     }
 
+    // path accepted by dbms_diskgroup; files without an alias use the numeric form +GROUP.file.incarnation
+    pub fn query_file_path(&self, ino: u64) -> Result<String, Error> {
+        let inode = Inode::from_ino(ino);
+
+        if !inode.is_view() {
+            return self.query_asm_alias_link(ino);
+        }
+
+        let group_row = self.select_diskgroup_by_number(inode.get_group_number())?;
+        let group_name: String = group_row.get("NAME")?;
+        let file_row = self.select_file_by_number(inode.get_group_number(), inode.get_alias_index())?;
+        let incarnation: u32 = file_row.get("INCARNATION")?;
+
+        Ok(format!("{}.{}.{}", group_name, inode.get_alias_index(), incarnation))
+    }
+
     pub fn proc_open(&self, ino: u64) -> Result<(u64, u32, u64, u64, u32), Error> {
-        let target_path = self.query_asm_alias_link(ino)?;
+        let target_path = self.query_file_path(ino)?;
 
         let (filetype, filesize_asm, filesize_fs, blksize) = self.proc_getfilettr(&target_path)?;
 
         let mut stmt = self.conn.statement("begin dbms_diskgroup.open(:b_target, :b_mode, :b_filetype, :b_blksize, :b_handle, :b_pblksize, :b_filesize); end;").build()?;
-        stmt.execute(&[&target_path, &"r", &filetype, &blksize, &OracleType::Int64, &OracleType::Int64, &filesize_asm])?;
+        self.execute(&mut stmt, &[&target_path, &"r", &filetype, &blksize, &OracleType::Int64, &OracleType::Int64, &filesize_asm])?;
 
         let handle: u64 = stmt.bind_value(5)?;
         let _pblksize: u64 = stmt.bind_value(6)?;   // physical block size
@@ -573,9 +1197,9 @@ impl OracleConnection {
         Ok((handle, blksize, filesize_asm, filesize_fs, filetype))
     }
 
-    pub fn proc_open_raw(&self, ino: u64, mirror: u8) -> Result<RawOpenFileHandle, Error> {
+    pub fn proc_open_raw(&self, ino: u64, mirror: u8, hybrid: bool) -> Result<RawOpenFileHandle, Error> {
         let inode :Inode = Inode::from_ino(ino);
-        let row = self.select_alias_file_by_reference_index_and_alias_index(inode.get_reference_index(), inode.get_alias_index())?;
+        let row = self.select_file_by_ino(ino)?;
         let file_number :u32 = row.get("FILE_NUMBER")?;
         let file_size_bytes = row.get("BYTES")?;
         let file_type :String = row.get("TYPE")?;
@@ -583,6 +1207,8 @@ impl OracleConnection {
         let group_number = inode.get_group_number();
         let fine_stripe_count :u32;
 
+        self.check_diskgroup_number(group_number)?;
+
         let striped :u8 = match striped.as_str() {
             "COARSE" => ASM_STRIPED_COARSE,
             "FINE" => ASM_STRIPED_FINE,
@@ -616,27 +1242,30 @@ impl OracleConnection {
 
         let disk_list :HashMap<u16, String> = self.query_asm_disks(group_number)?;
        
-        let disk_list_open :HashMap<u16, File> = disk_list
-            .into_iter()
-            .map(|(disk_number, block_device)| {
-                let file = match File::open(&block_device) {
-                    Ok(f) => f,
-                    Err(e) => {
-                        error!("Could not open block device {}: {}", &block_device, e);
-                        return Err(Error::new(ErrorKind::Other, format!("Could not open block device {}: {}", &block_device, e)));
-                    }
-                };
-                Ok((disk_number, file))
-            })
-            .collect::<Result<HashMap<u16, File>, Error>>()?;
+        let mut disk_list_open :HashMap<u16, File> = HashMap::new();
+        for (disk_number, block_device) in disk_list {
+            match devices::open(&block_device) {
+                Ok(file) => {
+                    disk_list_open.insert(disk_number, file);
+                },
+                // -o hybrid: the extents on this disk are read through dbms_diskgroup instead
+                Err(e) if hybrid => debug!("Block device {} not accessible, read through dbms_diskgroup: {}", &block_device, e),
+                Err(e) => {
+                    error!("Could not open block device {}: {}", &block_device, e);
+                    return Err(Error::new(ErrorKind::Other, format!("Could not open block device {}: {}", &block_device, e)));
+                }
+            }
+        }
 
         let retval = RawOpenFileHandle {
+            ino,
             au_list,
             au_size,
             file_size_bytes,
             file_type,
             disk_list: disk_list_open,
             file_number,
+            group_number,
             striped,
             fine_stripe_count
         };
@@ -646,7 +1275,7 @@ impl OracleConnection {
 
     pub fn proc_close(&self, fd: u64) -> Result<(), Error> {
         let mut stmt = self.conn.statement("begin dbms_diskgroup.close(:b_handle); end;").build()?;
-        stmt.execute(&[&fd])?;
+        self.execute(&mut stmt, &[&fd])?;
         Ok(())
     }
 
@@ -657,7 +1286,7 @@ impl OracleConnection {
 
         println!(".... dbms_diskgroup.read params: handle={}, offset_in_blocks={}, amount_in_blocks={}, amount_in_bytes={}", handle, offset_in_blocks, amount_in_blocks, amount_in_bytes);
 
-        stmt.execute(&[
+        self.execute(&mut stmt, &[
             &handle,                              // IN
             &offset_in_blocks,                    // IN
             &mut amount_in_bytes,                 // IN OUT
@@ -771,6 +1400,14 @@ impl OracleConnection {
     }
 }
 
+// v$asm_disk.path is either a device path or an AFD label ("AFD:DATA1")
+fn resolve_disk_path(path: String) -> String {
+    match path.strip_prefix("AFD:") {
+        Some(label) => get_afd_map().get(label).expect("Expected 'afdtool -getdevlist' to have value for all AFD disks in v$asm_disk").clone(),
+        None => path,
+    }
+}
+
 fn oracle_timestamp_to_system_time(ts: &Timestamp) -> SystemTime {
     let nd = NaiveDate::from_ymd_opt(ts.year(), ts.month(), ts.day()).unwrap().and_hms_opt(ts.hour(), ts.minute(), ts.second()).unwrap();
     let datetime_utc: DateTime<Utc> = DateTime::<Utc>::from_naive_utc_and_offset(nd, Utc);
@@ -3,12 +3,21 @@ use std::ffi::{CString, OsStr, OsString};
 use std::fs;
 use std::os::unix::ffi::{OsStrExt, OsStringExt};
 use std::path::PathBuf;
+use std::thread;
+use crate::notify;
+use crate::wait::{Deadline, POLL_INTERVAL};
 
 const BOOTSTRAP_GUARD: &str = "ASMFS_ENV_BOOTSTRAP";
-const PMON_PREFIX: &[u8] = b"asm_pmon_+ASM";
-
-
-pub(crate) fn bootstrap_oracle_env(args: &[OsString]) {
+const PMON_PREFIX: &[u8] = b"asm_pmon_+";
+const DEFAULT_SID_PREFIX: &[u8] = b"+ASM"; // without --sid, +APX proxies and the like are not considered
+
+/*
+ * --sid / -o sid= and --oracle-home / -o oracle_home= select the instance and Oracle home instead
+ * of the lowest-PID asm_pmon_+ASM* process. Either one bootstraps the environment even when
+ * ORACLE_HOME, ORACLE_SID and LD_LIBRARY_PATH are already set, and an explicit instance that is
+ * not running is an error rather than a skipped bootstrap.
+ */
+pub(crate) fn bootstrap_oracle_env(args: &[OsString], sid: Option<&str>, oracle_home: Option<&str>, wait: Option<Deadline>) {
 
     // do nothing if ASMFS_ENV_BOOTSTRAP is already set
     if env::var_os(BOOTSTRAP_GUARD).is_some() {
@@ -22,21 +31,54 @@ pub(crate) fn bootstrap_oracle_env(args: &[OsString]) {
     if existing_oracle_home.is_some()
         && existing_oracle_sid.is_some()
         && existing_library_path.is_some()
+        && sid.is_none()
+        && oracle_home.is_none()
     {
         // Everything was supplied manually. Do not inspect or validate it, just use it.
         // println!("Oracle environment already set (ORACLE_SID, ORACLE_HOME, LD_LIBRARY_PATH). Skipping bootstrap.");
         return;
     }
 
-    let discovered_instance = find_asm_instance();
-    let Some((pid, sid)) = discovered_instance.as_ref() else {
-        eprintln!("No ASM instance found. Skipping bootstrap.");
-        return;
+    let sid = sid.map(OsStr::new);
+
+    let discovered_instance = match wait {
+        Some(deadline) => Some(wait_for_asm_instance(sid, deadline)),
+        None => find_asm_instance(sid),
     };
 
-    let Some(home) = oracle_home_from_executable(*pid) else {
-        eprintln!("No Oracle home found for ASM instance. Skipping bootstrap.");
-        return;
+    if discovered_instance.is_none() {
+        if let Some(sid) = sid {
+            eprintln!("asmfs: ASM instance {} is not running{}", sid.to_string_lossy(), running_instances_hint());
+            std::process::exit(1);
+        }
+
+        if oracle_home.is_none() {
+            eprintln!("No ASM instance found. Skipping bootstrap.");
+            return;
+        }
+    }
+
+    let home = match (oracle_home, &discovered_instance) {
+        (Some(home), _) => {
+            let home = PathBuf::from(home);
+            if !home.join("lib").is_dir() {
+                eprintln!("asmfs: {} is not an Oracle home (it has no lib directory)", home.display());
+                std::process::exit(1);
+            }
+            home
+        }
+        (None, Some((pid, instance_sid))) => match oracle_home_from_executable(*pid) {
+            Some(home) => home,
+            None if sid.is_some() => {
+                eprintln!("asmfs: unable to find the Oracle home of {}, use --oracle-home", instance_sid.to_string_lossy());
+                std::process::exit(1);
+            }
+            None => {
+                eprintln!("No Oracle home found for ASM instance. Skipping bootstrap.");
+                return;
+            }
+        },
+        (None, None) => return, // handled above
     };
 
     let lib_dir = home.join("lib");
@@ -50,18 +92,28 @@ pub(crate) fn bootstrap_oracle_env(args: &[OsString]) {
         _ => lib_dir.as_os_str().to_os_string(),
     };
 
-    // SAFETY: this function is called before logging, clap, FUSE, or any
-    // other code that could create another thread. This is why this oraenv.rs intentionally
-    // avoids using info!() or warn!() calls.
+    // SAFETY: this function is called before Oracle, FUSE, or any other code that could
+    // create another thread. This is why this oraenv.rs intentionally avoids using info!()
+    // or warn!() calls.
     unsafe {
         env::set_var("ORACLE_HOME", &home);
-        env::set_var("ORACLE_SID", sid);
         env::set_var("LD_LIBRARY_PATH", new_library_path.clone());
         env::set_var(BOOTSTRAP_GUARD, "1");
+
+        // --oracle-home alone, without a local instance, e.g. for --conn
+        if let Some((_, sid)) = &discovered_instance {
+            env::set_var("ORACLE_SID", sid);
+        }
+
+        if let Some((name, value)) = wait.and_then(|deadline| deadline.env_var()) {
+            env::set_var(name, value);
+        }
     }
 
     println!("Re-executing with:");
-    println!("  ORACLE_SID={}", sid.to_string_lossy());
+    if let Some((_, sid)) = &discovered_instance {
+        println!("  ORACLE_SID={}", sid.to_string_lossy());
+    }
     println!("  ORACLE_HOME={}", home.display());
     println!("  LD_LIBRARY_PATH={}", new_library_path.display());
     println!("  (to avoid this message, set 'oraenv' by yourself before running asmfs binary)");
@@ -71,10 +123,12 @@ pub(crate) fn bootstrap_oracle_env(args: &[OsString]) {
 }
 
 /**
- * Returns ORACLE_SID of the ASM instance and its PID.
+ * Returns PID and ORACLE_SID of every running asm_pmon_+* process, lowest PID first.
  */
-fn find_asm_instance() -> Option<(u32, OsString)> {
-    let proc_entries = fs::read_dir("/proc").ok()?;
+fn running_asm_instances() -> Vec<(u32, OsString)> {
+    let Ok(proc_entries) = fs::read_dir("/proc") else {
+        return Vec::new();
+    };
     let mut matches = Vec::new();
 
     for entry in proc_entries.flatten() {
@@ -96,7 +150,6 @@ fn find_asm_instance() -> Option<(u32, OsString)> {
             .next()
             .unwrap_or_default();
 
-        // Ignore non-ASM processes, including asm_pmon_+APX.
         if !command.starts_with(PMON_PREFIX) {
             continue;
         }
@@ -116,19 +169,75 @@ fn find_asm_instance() -> Option<(u32, OsString)> {
         }
     }
 
-    // If several ASM instances are found, use the one with the lowest PID.
     matches.sort_by_key(|(pid, _)| *pid);
+    matches
+}
+
+/**
+ * Returns ORACLE_SID of the ASM instance and its PID: the one named by --sid, otherwise the
+ * +ASM* instance with the lowest PID.
+ */
+fn find_asm_instance(sid: Option<&OsStr>) -> Option<(u32, OsString)> {
+    let instances = running_asm_instances();
+
+    if let Some(sid) = sid {
+        return instances.into_iter().find(|(_, running)| running == sid);
+    }
+
+    // Ignore asm_pmon_+APX and other non-ASM instances.
+    let matches: Vec<(u32, OsString)> = instances
+        .into_iter()
+        .filter(|(_, running)| running.as_bytes().starts_with(DEFAULT_SID_PREFIX))
+        .collect();
 
     if matches.len() > 1 {
+        let sids: Vec<_> = matches.iter().map(|(pid, sid)| format!("{} (pid {})", sid.to_string_lossy(), pid)).collect();
         eprintln!(
-            "asmfs: warning: multiple ASM instances found; using pid {}",
-            matches[0].0
+            "asmfs: warning: multiple ASM instances found: {}; using {}, choose another one with --sid",
+            sids.join(", "),
+            matches[0].1.to_string_lossy()
         );
     }
 
     matches.into_iter().next()
 }
 
+// " (running: +ASM1, +APX1)" for the error about an --sid that is not running
+fn running_instances_hint() -> String {
+    let sids: Vec<_> = running_asm_instances().iter().map(|(_, sid)| sid.to_string_lossy().into_owned()).collect();
+
+    if sids.is_empty() {
+        " (no ASM instance is running)".to_string()
+    } else {
+        format!(" (running: {})", sids.join(", "))
+    }
+}
+
+// --wait-for-asm: polls for the asm_pmon process until it appears or the deadline passes
+fn wait_for_asm_instance(sid: Option<&OsStr>, deadline: Deadline) -> (u32, OsString) {
+    let wanted = sid.map_or("an ASM instance (asm_pmon_+ASM*)".to_string(), |sid| format!("ASM instance {}", sid.to_string_lossy()));
+    let mut reported = false;
+
+    loop {
+        if let Some(instance) = find_asm_instance(sid) {
+            return instance;
+        }
+
+        if deadline.expired() {
+            eprintln!("asmfs: {} did not start before the --wait-for-asm timeout", wanted);
+            std::process::exit(1);
+        }
+
+        if !reported {
+            eprintln!("Waiting for {} to start...", wanted);
+            notify::status(&format!("Waiting for {wanted} to start"));
+            reported = true;
+        }
+
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
 fn oracle_home_from_executable(pid: u32) -> Option<PathBuf> {
     let executable = fs::read_link(format!("/proc/{pid}/exe")).ok()?;
 
@@ -0,0 +1,199 @@
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use fuser::{FileAttr, FileType};
+use log::{debug, warn};
+use crate::fuse::AsmFS;
+use crate::inode::Inode;
+
+/*
+ * Ownership and mode bits of every entry:
+ *
+ *   - by default everything belongs to --uid/--gid; files get `file_mode` (0444), directories
+ *     `dir_mode` (0555), both reduced by `umask`
+ *   - with -o asm_acl, files take owner, usergroup and permissions from ASM File Access Control
+ *     (v$asm_file, v$asm_user, v$asm_usergroup). ASM names are mapped to local users and groups by
+ *     name; whatever cannot be mapped falls back to the defaults above. Directories have no ACL
+ *     in ASM and keep the defaults. The kernel enforces the result (default_permissions).
+ */
+
+pub const DEFAULT_FILE_MODE: u32 = 0o444;
+pub const DEFAULT_DIR_MODE: u32 = 0o555;
+
+// ASM user and usergroup names resolved to local ids; None is cached too, as a miss is the slow case
+#[derive(Default)]
+pub struct IdCache {
+    users: Mutex<HashMap<String, Option<u32>>>,
+    groups: Mutex<HashMap<String, Option<u32>>>,
+    pub hits: AtomicU64,
+    pub misses: AtomicU64,
+}
+
+impl IdCache {
+    pub fn clear(&self) {
+        self.users.lock().unwrap().clear();
+        self.groups.lock().unwrap().clear();
+    }
+
+    fn uid(&self, name: &str) -> Option<u32> {
+        self.cached(&self.users, name, lookup_uid)
+    }
+
+    fn gid(&self, name: &str) -> Option<u32> {
+        self.cached(&self.groups, name, lookup_gid)
+    }
+
+    fn cached(&self, cache: &Mutex<HashMap<String, Option<u32>>>, name: &str, lookup: fn(&str) -> Option<u32>) -> Option<u32> {
+        let mut cache = cache.lock().unwrap();
+
+        if let Some(id) = cache.get(name) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return *id;
+        }
+
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        *cache.entry(name.to_string()).or_insert(lookup(name))
+    }
+}
+
+impl AsmFS {
+    pub(crate) fn with_configured_access(&self, mut attr: FileAttr) -> FileAttr {
+        attr.uid = self.config.owner_uid;
+        attr.gid = self.config.owner_gid;
+        attr.perm = match attr.kind {
+            FileType::Directory => self.config.dir_mode,
+            FileType::Symlink => 0o777, // never checked by the kernel; the target's mode counts
+            _ => self.config.file_mode,
+        } as u16;
+
+        if self.config.asm_acl && attr.kind == FileType::RegularFile && !Inode::from_ino(attr.ino.0).is_synthetic() {
+            self.apply_asm_acl(&mut attr);
+        }
+
+        attr.perm &= !(self.config.umask as u16) & 0o7777;
+        attr
+    }
+
+    fn apply_asm_acl(&self, attr: &mut FileAttr) {
+        let acl = match self.ora().unwrap().query_file_acl(attr.ino.0) {
+            Ok(acl) => acl,
+            Err(e) => {
+                warn!("unable to query ASM access control of inode {}: {}", attr.ino, e);
+                return;
+            }
+        };
+
+        if let Some(uid) = acl.owner.as_deref().and_then(|owner| self.id_cache.uid(owner)) {
+            attr.uid = uid;
+        } else if let Some(owner) = &acl.owner {
+            debug!("ASM user '{}' has no local account; inode {} keeps the default owner", owner, attr.ino);
+        }
+
+        if let Some(gid) = acl.usergroup.as_deref().and_then(|usergroup| self.id_cache.gid(usergroup)) {
+            attr.gid = gid;
+        } else if let Some(usergroup) = &acl.usergroup {
+            debug!("ASM usergroup '{}' has no local group; inode {} keeps the default group", usergroup, attr.ino);
+        }
+
+        if let Some(perm) = acl.permissions.as_deref().and_then(parse_permissions) {
+            attr.perm = perm;
+        }
+    }
+}
+
+// rw-r----- => 0o640
+fn parse_permissions(permissions: &str) -> Option<u16> {
+    let bits = permissions.as_bytes();
+
+    if bits.len() != 9 {
+        return None;
+    }
+
+    let mut mode = 0u16;
+    for (index, bit) in bits.iter().enumerate() {
+        mode <<= 1;
+        match (index % 3, bit) {
+            (0, b'r') | (1, b'w') | (2, b'x') => mode |= 1,
+            (_, b'-') => {}
+            _ => return None,
+        }
+    }
+
+    Some(mode)
+}
+
+// octal mount option values: 0440, 440 or 0o440
+pub fn parse_mode(value: &str) -> Result<u32, String> {
+    let digits = value.trim_start_matches("0o");
+    u32::from_str_radix(digits, 8)
+        .ok()
+        .filter(|mode| *mode <= 0o7777)
+        .ok_or_else(|| format!("'{value}' is not an octal mode"))
+}
+
+pub(crate) fn lookup_uid(name: &str) -> Option<u32> {
+    let name = CString::new(name).ok()?;
+    let mut buffer = vec![0 as libc::c_char; 16384];
+    let mut passwd: libc::passwd = unsafe { std::mem::zeroed() };
+    let mut result: *mut libc::passwd = std::ptr::null_mut();
+
+    let rc = unsafe { libc::getpwnam_r(name.as_ptr(), &mut passwd, buffer.as_mut_ptr(), buffer.len(), &mut result) };
+    if rc == 0 && !result.is_null() { Some(passwd.pw_uid) } else { None }
+}
+
+pub(crate) fn lookup_gid(name: &str) -> Option<u32> {
+    let name = CString::new(name).ok()?;
+    let mut buffer = vec![0 as libc::c_char; 16384];
+    let mut group: libc::group = unsafe { std::mem::zeroed() };
+    let mut result: *mut libc::group = std::ptr::null_mut();
+
+    let rc = unsafe { libc::getgrnam_r(name.as_ptr(), &mut group, buffer.as_mut_ptr(), buffer.len(), &mut result) };
+    if rc == 0 && !result.is_null() { Some(group.gr_gid) } else { None }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn permissions_strings_become_modes() {
+        assert_eq!(parse_permissions("rw-r-----"), Some(0o640));
+        assert_eq!(parse_permissions("rwxrwxrwx"), Some(0o777));
+        assert_eq!(parse_permissions("---------"), Some(0));
+        assert_eq!(parse_permissions("r--r--r--"), Some(0o444));
+        assert_eq!(parse_permissions("rw-r----x"), Some(0o641));
+        assert_eq!(parse_permissions("rw-r-x---"), Some(0o650));
+    }
+
+    #[test]
+    fn malformed_permissions_are_rejected() {
+        assert_eq!(parse_permissions("-rw-r-----"), None);
+        assert_eq!(parse_permissions("rw-r----"), None);
+        assert_eq!(parse_permissions(""), None);
+        assert_eq!(parse_permissions("wr-r-----"), None);
+        assert_eq!(parse_permissions("rw-r--x--"), None);
+        assert_eq!(parse_permissions("rwsr-----"), None);
+        assert_eq!(parse_permissions("RW-R-----"), None);
+    }
+
+    #[test]
+    fn modes_are_octal() {
+        assert_eq!(parse_mode("0440"), Ok(0o440));
+        assert_eq!(parse_mode("440"), Ok(0o440));
+        assert_eq!(parse_mode("0o440"), Ok(0o440));
+        assert_eq!(parse_mode("7777"), Ok(0o7777));
+        assert_eq!(parse_mode("0"), Ok(0));
+    }
+
+    #[test]
+    fn invalid_modes_are_rejected() {
+        assert!(parse_mode("10000").is_err());
+        assert!(parse_mode("0o17777").is_err());
+        assert!(parse_mode("0448").is_err());
+        assert!(parse_mode("rw-r-----").is_err());
+        assert!(parse_mode("").is_err());
+        assert!(parse_mode("0x1ff").is_err());
+        assert_eq!(parse_mode("044o").err().unwrap(), "'044o' is not an octal mode");
+    }
+}
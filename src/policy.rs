@@ -0,0 +1,254 @@
+use std::fs;
+use fuser::FileType;
+use crate::permissions::{lookup_gid, lookup_uid};
+
+/*
+ * Per-user access policy (-o policy_file=). Each line grants one uid or gid a list of path
+ * prefixes:
+ *
+ *   # subject      prefixes
+ *   uid=1001       +FRA
+ *   group=backup   +FRA +DATA/ORCL/ARCHIVELOG
+ *   user=oracle    +DATA +FRA .asmfs
+ *
+ * A subject is uid=, gid=, user= or group=; names are resolved when the file is loaded. Groups
+ * match the primary gid of the request and the supplementary groups of the calling process.
+ * A prefix covers itself and everything below it. `.asmfs` stands for the whole /.asmfs/
+ * directory, which includes raw access to the ASM disks.
+ *
+ * Once a policy is loaded, a request is allowed only when one of the caller's prefixes covers
+ * the path. Directories above a prefix stay walkable, but list only what leads to a granted
+ * path. uid 0 is never restricted: root can read the ASM disks directly anyway.
+ */
+
+enum Subject {
+    Uid(u32),
+    Gid(u32),
+}
+
+pub struct AccessPolicy {
+    rules: Vec<(Subject, Vec<String>)>,
+}
+
+// the process behind a FUSE request
+pub struct Requester {
+    pub uid: u32,
+    pub gid: u32,
+    pub pid: u32,
+}
+
+impl AccessPolicy {
+    pub fn load(path: &str) -> Result<AccessPolicy, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("Unable to read policy file {path}: {e}"))?;
+
+        let mut rules = Vec::new();
+
+        for (line_number, line) in content.lines().enumerate() {
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut fields = line.split_whitespace();
+            let subject = fields.next().unwrap_or_default();
+            let subject = parse_subject(subject).map_err(|e| format!("{path}:{}: {e}", line_number + 1))?;
+            let prefixes: Vec<String> = fields.map(normalize_prefix).collect();
+
+            if prefixes.is_empty() {
+                return Err(format!("{path}:{}: no path prefixes given", line_number + 1));
+            }
+
+            rules.push((subject, prefixes));
+        }
+
+        Ok(AccessPolicy { rules })
+    }
+
+    // path is None for the root directory, which everybody may see
+    pub fn allows(&self, requester: &Requester, path: Option<&str>, kind: FileType) -> bool {
+        if requester.uid == 0 {
+            return true;
+        }
+
+        let path = match path {
+            Some(path) => path,
+            None => return true,
+        };
+
+        let mut groups: Option<Vec<u32>> = None;
+
+        let prefixes = self.rules.iter().filter(|(subject, _)| match subject {
+            Subject::Uid(uid) => *uid == requester.uid,
+            Subject::Gid(gid) => {
+                *gid == requester.gid || groups.get_or_insert_with(|| supplementary_groups(requester.pid)).contains(gid)
+            }
+        });
+
+        prefixes
+            .flat_map(|(_, prefixes)| prefixes.iter())
+            .any(|prefix| covers(prefix, path) || (kind == FileType::Directory && covers(path, prefix)))
+    }
+}
+
+fn parse_subject(subject: &str) -> Result<Subject, String> {
+    let invalid = || format!("invalid subject '{subject}', expected uid=, gid=, user= or group=");
+
+    match subject.split_once('=').ok_or_else(invalid)? {
+        ("uid", uid) => uid.parse().map(Subject::Uid).map_err(|_| invalid()),
+        ("gid", gid) => gid.parse().map(Subject::Gid).map_err(|_| invalid()),
+        ("user", name) => lookup_uid(name).map(Subject::Uid).ok_or_else(|| format!("unknown user '{name}'")),
+        ("group", name) => lookup_gid(name).map(Subject::Gid).ok_or_else(|| format!("unknown group '{name}'")),
+        _ => Err(invalid()),
+    }
+}
+
+// fra/ => +FRA, /.asmfs => .asmfs
+fn normalize_prefix(prefix: &str) -> String {
+    let prefix = prefix.trim_matches('/');
+
+    if prefix.eq_ignore_ascii_case(".asmfs") || prefix.starts_with('+') {
+        prefix.to_string()
+    } else {
+        format!("+{}", prefix)
+    }
+}
+
+/*
+ * Whether `prefix` is `path` or one of its ancestors, ignoring case like ASM does. Files without
+ * aliases go by their numeric name (+DATA.256.1), which only a whole diskgroup covers.
+ */
+fn covers(prefix: &str, path: &str) -> bool {
+    match path.get(..prefix.len()) {
+        Some(head) if head.eq_ignore_ascii_case(prefix) => {
+            let rest = &path[prefix.len()..];
+            rest.is_empty() || rest.starts_with('/') || (rest.starts_with('.') && !prefix.contains('/'))
+        }
+        _ => false,
+    }
+}
+
+// the "Groups:" line of /proc/<pid>/status; empty if the process is gone
+fn supplementary_groups(pid: u32) -> Vec<u32> {
+    fs::read_to_string(format!("/proc/{}/status", pid))
+        .unwrap_or_default()
+        .lines()
+        .find_map(|line| line.strip_prefix("Groups:"))
+        .map(|groups| groups.split_whitespace().filter_map(|gid| gid.parse().ok()).collect())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy_file(name: &str, content: &str) -> String {
+        let path = std::env::temp_dir().join(format!("asmfs-policy-{}-{}", std::process::id(), name));
+        fs::write(&path, content).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    fn load(name: &str, content: &str) -> Result<AccessPolicy, String> {
+        let path = policy_file(name, content);
+        let policy = AccessPolicy::load(&path);
+        fs::remove_file(&path).unwrap();
+        policy
+    }
+
+    // pid 0 has no /proc entry, so no supplementary groups
+    fn requester(uid: u32, gid: u32) -> Requester {
+        Requester { uid, gid, pid: 0 }
+    }
+
+    #[test]
+    fn prefix_covers_itself_and_below() {
+        assert!(covers("+DATA", "+DATA"));
+        assert!(covers("+DATA", "+DATA/ORCL/DATAFILE/USERS.259.1"));
+        assert!(!covers("+DATA", "+DATA2"));
+        assert!(!covers("+DATA", "+DATA2/ORCL"));
+        assert!(!covers("+DATA/ORCL", "+DATA/ORCL2"));
+        assert!(!covers("+DATA/ORCL", "+DATA"));
+    }
+
+    #[test]
+    fn numeric_names_are_covered_by_the_diskgroup_only() {
+        assert!(covers("+DATA", "+DATA.256.1"));
+        assert!(!covers("+DATA/ORCL", "+DATA/ORCL.256.1"));
+        assert!(!covers("+DATA/ORCL", "+DATA.256.1"));
+    }
+
+    #[test]
+    fn prefixes_are_normalized() {
+        assert_eq!(normalize_prefix("fra/"), "+fra");
+        assert_eq!(normalize_prefix("/+DATA/ORCL/"), "+DATA/ORCL");
+        assert_eq!(normalize_prefix("/.asmfs"), ".asmfs");
+    }
+
+    #[test]
+    fn subjects_are_parsed() {
+        assert!(matches!(parse_subject("uid=1001"), Ok(Subject::Uid(1001))));
+        assert!(matches!(parse_subject("gid=54321"), Ok(Subject::Gid(54321))));
+        assert!(matches!(parse_subject("user=root"), Ok(Subject::Uid(0))));
+        assert!(parse_subject("uid=oracle").is_err());
+        assert!(parse_subject("login=oracle").is_err());
+        assert!(parse_subject("1001").is_err());
+        assert!(parse_subject("user=no-such-user-asmfs").is_err());
+    }
+
+    #[test]
+    fn ancestor_directories_are_walkable_but_not_ancestor_files() {
+        let policy = load("ancestors", "uid=1001 +DATA/ORCL/ARCHIVELOG\n").unwrap();
+        let user = requester(1001, 1001);
+
+        assert!(policy.allows(&user, Some("+DATA"), FileType::Directory));
+        assert!(policy.allows(&user, Some("+DATA/ORCL"), FileType::Directory));
+        assert!(policy.allows(&user, Some("+DATA/ORCL/ARCHIVELOG/arch_1.log"), FileType::RegularFile));
+
+        assert!(!policy.allows(&user, Some("+DATA/ORCL"), FileType::RegularFile));
+        assert!(!policy.allows(&user, Some("+DATA/ORCL/DATAFILE"), FileType::Directory));
+        assert!(!policy.allows(&user, Some("+DATA.256.1"), FileType::RegularFile));
+        assert!(!policy.allows(&user, Some("+FRA"), FileType::Directory));
+    }
+
+    #[test]
+    fn matching_ignores_case() {
+        let policy = load("case", "uid=1001 +data/orcl\n").unwrap();
+        let user = requester(1001, 1001);
+
+        assert!(policy.allows(&user, Some("+DATA/ORCL/DATAFILE/USERS.259.1"), FileType::RegularFile));
+        assert!(policy.allows(&user, Some("+Data/Orcl"), FileType::Directory));
+    }
+
+    #[test]
+    fn rules_match_uid_or_primary_gid() {
+        let policy = load("subjects", "# backups\nuid=1001 +FRA\n\ngid=2000 .asmfs\n").unwrap();
+
+        assert!(policy.allows(&requester(1001, 1001), Some("+FRA/ORCL"), FileType::Directory));
+        assert!(!policy.allows(&requester(1001, 1001), Some(".asmfs/disks"), FileType::Directory));
+        assert!(policy.allows(&requester(1002, 2000), Some(".asmfs/disks"), FileType::Directory));
+        assert!(!policy.allows(&requester(1002, 2000), Some("+FRA/ORCL"), FileType::Directory));
+        assert!(!policy.allows(&requester(1003, 1003), Some("+FRA"), FileType::Directory));
+    }
+
+    #[test]
+    fn root_and_the_mountpoint_bypass_the_policy() {
+        let policy = load("root", "uid=1001 +FRA\n").unwrap();
+
+        assert!(policy.allows(&requester(0, 0), Some("+DATA/ORCL"), FileType::RegularFile));
+        assert!(policy.allows(&requester(1003, 1003), None, FileType::Directory));
+    }
+
+    #[test]
+    fn parse_errors_carry_path_and_line() {
+        let path = policy_file("errors", "# comment\nuid=1001 +FRA\nowner=1002 +DATA\n");
+        let error = AccessPolicy::load(&path).err().unwrap();
+        assert!(error.starts_with(&format!("{path}:3: invalid subject 'owner=1002'")), "{error}");
+
+        fs::write(&path, "uid=1001\n").unwrap();
+        let error = AccessPolicy::load(&path).err().unwrap();
+        assert_eq!(error, format!("{path}:1: no path prefixes given"));
+
+        fs::remove_file(&path).unwrap();
+    }
+}
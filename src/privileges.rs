@@ -0,0 +1,258 @@
+use std::ffi::CString;
+use std::fs::OpenOptions;
+use std::io;
+use std::mem::size_of;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::Path;
+use log::{info, warn};
+use crate::config::MountConfig;
+
+/*
+ * -o run_as=USER: asmfs started as root, e.g. for AFD whose disks only root may open, becomes USER
+ * as soon as the disks are open (devices.rs) and before mounting. It takes the uid and primary
+ * group of USER, drops all supplementary groups, and with root gives up every capability.
+ *
+ * -o confine: once mounted, asmfs restricts itself further, for the rest of its life:
+ *
+ *   Landlock  files may be read anywhere, but written or created only where asmfs and the Oracle
+ *             client write (log file, audit log, sockets, $ORACLE_BASE, $ORACLE_HOME, /tmp), and
+ *             programs run only from $ORACLE_HOME and the system directories; skipped with a
+ *             warning on kernels without Landlock
+ *   seccomp   system calls asmfs has no use for (ptrace, mount, module loading, setuid, ...) fail
+ *             with EPERM
+ *
+ * Both need no_new_privs, after which the setuid fusermount3 cannot unmount anymore: unmount
+ * with `umount` or `fusermount3 -u` from outside, as asmfs@.service does.
+ */
+
+pub fn lookup_user(user: &str) -> Result<(u32, u32), String> {
+    let name = CString::new(user).map_err(|_| format!("Invalid user name '{user}'"))?;
+    let mut buffer = vec![0 as libc::c_char; 16384];
+    let mut passwd: libc::passwd = unsafe { std::mem::zeroed() };
+    let mut result: *mut libc::passwd = std::ptr::null_mut();
+
+    let rc = match user.parse::<u32>() {
+        Ok(uid) => unsafe { libc::getpwuid_r(uid, &mut passwd, buffer.as_mut_ptr(), buffer.len(), &mut result) },
+        Err(_) => unsafe { libc::getpwnam_r(name.as_ptr(), &mut passwd, buffer.as_mut_ptr(), buffer.len(), &mut result) },
+    };
+
+    if rc != 0 {
+        return Err(format!("Unable to look up user {user}: {}", io::Error::from_raw_os_error(rc)));
+    }
+    if result.is_null() {
+        return Err(format!("No such user: {user}"));
+    }
+    Ok((passwd.pw_uid, passwd.pw_gid))
+}
+
+// -o run_as= needs root, unless asmfs already runs as that user
+pub fn check_run_as(uid: u32) -> Result<(), String> {
+    let euid = unsafe { libc::geteuid() };
+    if euid != 0 && euid != uid {
+        return Err("-o run_as= requires starting asmfs as root".to_string());
+    }
+    Ok(())
+}
+
+pub fn run_as(uid: u32, gid: u32) -> Result<(), String> {
+    if unsafe { libc::geteuid() } == uid {
+        return Ok(());
+    }
+
+    // SAFETY: no pointers but the empty group list; glibc applies each change to every thread
+    unsafe {
+        if libc::setgroups(0, std::ptr::null()) != 0 {
+            return Err(format!("Unable to drop supplementary groups: {}", io::Error::last_os_error()));
+        }
+        if libc::setresgid(gid, gid, gid) != 0 {
+            return Err(format!("Unable to switch to gid {gid}: {}", io::Error::last_os_error()));
+        }
+        if libc::setresuid(uid, uid, uid) != 0 {
+            return Err(format!("Unable to switch to uid {uid}: {}", io::Error::last_os_error()));
+        }
+
+        // there must be no way back
+        if uid != 0 && libc::setuid(0) == 0 {
+            return Err("Still able to become root after -o run_as=".to_string());
+        }
+    }
+
+    info!("Running as uid={} gid={}", uid, gid);
+    Ok(())
+}
+
+pub fn confine(config: &MountConfig) -> Result<(), String> {
+    // SAFETY: plain prctl; required by both Landlock and seccomp for an unprivileged process
+    if unsafe { libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) } != 0 {
+        return Err(format!("Unable to set no_new_privs: {}", io::Error::last_os_error()));
+    }
+
+    landlock(config)?;
+    seccomp()?;
+    info!("Confined with Landlock and seccomp");
+    Ok(())
+}
+
+/*
+ * Landlock, with the structures of linux/landlock.h. Rules only apply to the calling thread and
+ * the threads it starts later, hence confine() runs before the control, metrics, watchdog and
+ * FUSE threads; only the audit log writer, which just writes to the file it has open, is older.
+ */
+
+const LANDLOCK_CREATE_RULESET_VERSION: u32 = 1 << 0;
+const LANDLOCK_RULE_PATH_BENEATH: u32 = 1;
+
+const ACCESS_FS_EXECUTE: u64 = 1 << 0;
+const ACCESS_FS_WRITE_FILE: u64 = 1 << 1;
+const ACCESS_FS_READ_FILE: u64 = 1 << 2;
+const ACCESS_FS_READ_DIR: u64 = 1 << 3;
+const ACCESS_FS_REMOVE_DIR: u64 = 1 << 4;
+const ACCESS_FS_REMOVE_FILE: u64 = 1 << 5;
+const ACCESS_FS_MAKE_DIR: u64 = 1 << 7;
+const ACCESS_FS_MAKE_REG: u64 = 1 << 8;
+const ACCESS_FS_MAKE_SOCK: u64 = 1 << 9;
+const ACCESS_FS_ABI_1: u64 = (1 << 13) - 1; // everything up to MAKE_SYM
+const ACCESS_FS_REFER: u64 = 1 << 13;       // ABI 2
+const ACCESS_FS_TRUNCATE: u64 = 1 << 14;    // ABI 3
+const ACCESS_FILE: u64 = ACCESS_FS_EXECUTE | ACCESS_FS_WRITE_FILE | ACCESS_FS_READ_FILE | ACCESS_FS_TRUNCATE; // rights a file (not a directory) can have
+
+const READ: u64 = ACCESS_FS_READ_FILE | ACCESS_FS_READ_DIR;
+const WRITE: u64 = ACCESS_FS_WRITE_FILE | ACCESS_FS_TRUNCATE | ACCESS_FS_REMOVE_FILE | ACCESS_FS_REMOVE_DIR
+    | ACCESS_FS_MAKE_REG | ACCESS_FS_MAKE_DIR | ACCESS_FS_MAKE_SOCK | ACCESS_FS_REFER;
+
+#[repr(C)]
+struct RulesetAttr {
+    handled_access_fs: u64,
+}
+
+#[repr(C, packed)]
+struct PathBeneathAttr {
+    allowed_access: u64,
+    parent_fd: i32,
+}
+
+fn landlock(config: &MountConfig) -> Result<(), String> {
+    // SAFETY: a NULL attribute with the VERSION flag only queries the ABI version
+    let abi = unsafe { libc::syscall(libc::SYS_landlock_create_ruleset, std::ptr::null::<RulesetAttr>(), 0, LANDLOCK_CREATE_RULESET_VERSION) };
+    if abi < 1 {
+        warn!("Landlock is not available ({}), -o confine uses seccomp only", io::Error::last_os_error());
+        return Ok(());
+    }
+
+    let handled = ACCESS_FS_ABI_1
+        | if abi >= 2 { ACCESS_FS_REFER } else { 0 }
+        | if abi >= 3 { ACCESS_FS_TRUNCATE } else { 0 };
+
+    let attr = RulesetAttr { handled_access_fs: handled };
+    // SAFETY: attr is a valid ruleset_attr of the given size
+    let fd = unsafe { libc::syscall(libc::SYS_landlock_create_ruleset, &attr, size_of::<RulesetAttr>(), 0) };
+    if fd < 0 {
+        return Err(format!("Unable to create Landlock ruleset: {}", io::Error::last_os_error()));
+    }
+    // SAFETY: a new descriptor owned by nobody else
+    let ruleset = unsafe { OwnedFd::from_raw_fd(fd as i32) };
+
+    let oracle_home = std::env::var("ORACLE_HOME").ok();
+    let oracle_base = std::env::var("ORACLE_BASE").ok();
+
+    // what asmfs writes to: its own files, sockets, and whatever the Oracle client writes (diag, traces)
+    let writable: Vec<&Path> = [config.log_file.as_deref(), config.audit_log.as_deref(), config.control_socket.as_deref(), config.metrics.as_deref().and_then(|metrics| metrics.strip_prefix("unix:"))]
+        .into_iter()
+        .flatten()
+        .filter_map(|file| Path::new(file).parent())
+        .chain([oracle_home.as_deref(), oracle_base.as_deref()].into_iter().flatten().map(Path::new))
+        .chain([Path::new("/tmp"), Path::new("/var/tmp")])
+        .collect();
+
+    let rules: Vec<(&Path, u64)> = [(Path::new("/"), READ)]
+        .into_iter()
+        .chain(writable.into_iter().map(|dir| (dir, READ | WRITE)))
+        .chain(oracle_home.as_deref().map(|home| (Path::new(home), READ | ACCESS_FS_EXECUTE)))
+        .chain(["/usr", "/lib", "/lib64", "/bin", "/sbin"].into_iter().map(|dir| (Path::new(dir), READ | ACCESS_FS_EXECUTE)))
+        .chain(["/dev/fuse", "/dev/null", "/dev/zero", "/dev/urandom"].into_iter().map(|dev| (Path::new(dev), ACCESS_FS_READ_FILE | ACCESS_FS_WRITE_FILE)))
+        .collect();
+
+    for (path, access) in rules {
+        // paths that do not exist need no rule; O_PATH does not open /dev/fuse for real
+        let Ok(file) = OpenOptions::new().read(true).custom_flags(libc::O_PATH).open(path) else {
+            continue;
+        };
+        let is_dir = file.metadata().is_ok_and(|metadata| metadata.is_dir());
+        let access = access & handled & if is_dir { u64::MAX } else { ACCESS_FILE };
+
+        let rule = PathBeneathAttr { allowed_access: access, parent_fd: file.as_raw_fd() };
+        // SAFETY: rule is a valid path_beneath_attr, both descriptors are open
+        let rc = unsafe { libc::syscall(libc::SYS_landlock_add_rule, ruleset.as_raw_fd(), LANDLOCK_RULE_PATH_BENEATH, &rule, 0) };
+        if rc != 0 {
+            return Err(format!("Unable to add Landlock rule for {}: {}", path.display(), io::Error::last_os_error()));
+        }
+    }
+
+    // SAFETY: no_new_privs is set, the ruleset descriptor is open
+    if unsafe { libc::syscall(libc::SYS_landlock_restrict_self, ruleset.as_raw_fd(), 0) } != 0 {
+        return Err(format!("Unable to enforce Landlock ruleset: {}", io::Error::last_os_error()));
+    }
+    Ok(())
+}
+
+/*
+ * seccomp: a deny list rather than an allow list, as the Oracle client is a closed box that may
+ * use any ordinary system call. The filter is synchronized to every thread (TSYNC).
+ */
+
+#[cfg(target_arch = "x86_64")]
+const AUDIT_ARCH: u32 = 0xC000_003E; // AUDIT_ARCH_X86_64
+#[cfg(target_arch = "aarch64")]
+const AUDIT_ARCH: u32 = 0xC000_00B7; // AUDIT_ARCH_AARCH64; the Oracle client exists for these two only
+
+const X32_SYSCALL_BIT: u32 = 0x4000_0000; // x32 numbers on x86_64 would bypass the list below
+
+const DENIED_SYSCALLS: &[libc::c_long] = &[
+    libc::SYS_ptrace, libc::SYS_process_vm_readv, libc::SYS_process_vm_writev,
+    libc::SYS_mount, libc::SYS_umount2, libc::SYS_pivot_root, libc::SYS_chroot,
+    libc::SYS_unshare, libc::SYS_setns,
+    libc::SYS_setuid, libc::SYS_setgid, libc::SYS_setreuid, libc::SYS_setregid,
+    libc::SYS_setresuid, libc::SYS_setresgid, libc::SYS_setgroups, libc::SYS_setfsuid, libc::SYS_setfsgid,
+    libc::SYS_init_module, libc::SYS_finit_module, libc::SYS_delete_module,
+    libc::SYS_kexec_load, libc::SYS_kexec_file_load, libc::SYS_reboot, libc::SYS_swapon, libc::SYS_swapoff,
+    libc::SYS_bpf, libc::SYS_perf_event_open, libc::SYS_userfaultfd, libc::SYS_open_by_handle_at,
+    libc::SYS_keyctl, libc::SYS_add_key, libc::SYS_request_key, libc::SYS_acct,
+];
+
+fn bpf_stmt(code: u32, k: u32) -> libc::sock_filter {
+    libc::sock_filter { code: code as u16, jt: 0, jf: 0, k }
+}
+
+fn bpf_jump(code: u32, k: u32, jt: u8, jf: u8) -> libc::sock_filter {
+    libc::sock_filter { code: code as u16, jt, jf, k }
+}
+
+fn seccomp() -> Result<(), String> {
+    let deny = libc::SECCOMP_RET_ERRNO | libc::EPERM as u32;
+
+    // struct seccomp_data: nr at offset 0, arch at offset 4
+    let mut filter = vec![
+        bpf_stmt(libc::BPF_LD | libc::BPF_W | libc::BPF_ABS, 4),
+        bpf_jump(libc::BPF_JMP | libc::BPF_JEQ | libc::BPF_K, AUDIT_ARCH, 1, 0),
+        bpf_stmt(libc::BPF_RET | libc::BPF_K, libc::SECCOMP_RET_KILL_PROCESS),
+        bpf_stmt(libc::BPF_LD | libc::BPF_W | libc::BPF_ABS, 0),
+        bpf_jump(libc::BPF_JMP | libc::BPF_JGE | libc::BPF_K, X32_SYSCALL_BIT, 0, 1),
+        bpf_stmt(libc::BPF_RET | libc::BPF_K, deny),
+    ];
+
+    for nr in DENIED_SYSCALLS {
+        filter.push(bpf_jump(libc::BPF_JMP | libc::BPF_JEQ | libc::BPF_K, *nr as u32, 0, 1));
+        filter.push(bpf_stmt(libc::BPF_RET | libc::BPF_K, deny));
+    }
+    filter.push(bpf_stmt(libc::BPF_RET | libc::BPF_K, libc::SECCOMP_RET_ALLOW));
+
+    let program = libc::sock_fprog { len: filter.len() as u16, filter: filter.as_mut_ptr() };
+
+    // SAFETY: program points to `filter`, which outlives the call; no_new_privs is set
+    match unsafe { libc::syscall(libc::SYS_seccomp, libc::SECCOMP_SET_MODE_FILTER, libc::SECCOMP_FILTER_FLAG_TSYNC, &program) } {
+        0 => Ok(()),
+        -1 => Err(format!("Unable to install seccomp filter: {}", io::Error::last_os_error())),
+        tid => Err(format!("Unable to install seccomp filter: thread {tid} could not be synchronized")),
+    }
+}
@@ -0,0 +1,218 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::RwLock;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Instant;
+use fuser::Errno;
+
+#[derive(Clone, Copy)]
+pub enum Op {
+    Lookup,
+    Getattr,
+    Readlink,
+    Open,
+    Read,
+    Release,
+    Readdir,
+    Statfs,
+}
+
+impl Op {
+    pub const ALL: [Op; 8] = [Op::Lookup, Op::Getattr, Op::Readlink, Op::Open, Op::Read, Op::Release, Op::Readdir, Op::Statfs];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Op::Lookup => "lookup",
+            Op::Getattr => "getattr",
+            Op::Readlink => "readlink",
+            Op::Open => "open",
+            Op::Read => "read",
+            Op::Release => "release",
+            Op::Readdir => "readdir",
+            Op::Statfs => "statfs",
+        }
+    }
+}
+
+// upper bounds of the latency histogram buckets, in microseconds; the last bucket is unbounded
+pub const LATENCY_BUCKETS_US: [u64; 10] = [100, 250, 1_000, 2_500, 10_000, 25_000, 100_000, 250_000, 1_000_000, 5_000_000];
+
+pub struct Latency {
+    pub count: AtomicU64,
+    pub total_us: AtomicU64,
+    pub max_us: AtomicU64,
+    pub buckets: [AtomicU64; LATENCY_BUCKETS_US.len()], // not cumulative; one sample lands in one bucket
+}
+
+impl Latency {
+    pub const fn new() -> Latency {
+        Latency {
+            count: AtomicU64::new(0),
+            total_us: AtomicU64::new(0),
+            max_us: AtomicU64::new(0),
+            buckets: [const { AtomicU64::new(0) }; LATENCY_BUCKETS_US.len()],
+        }
+    }
+
+    // Latency is recorded when the returned timer goes out of scope.
+    pub fn time(&self) -> OpTimer<'_> {
+        OpTimer {
+            counters: self,
+            start: Instant::now(),
+        }
+    }
+
+    fn record(&self, elapsed_us: u64) {
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.total_us.fetch_add(elapsed_us, Ordering::Relaxed);
+        self.max_us.fetch_max(elapsed_us, Ordering::Relaxed);
+
+        if let Some(bucket) = LATENCY_BUCKETS_US.iter().position(|bound| elapsed_us <= *bound) {
+            self.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+// every SQL statement of every connection, see OracleConnection::query()
+pub static SQL_LATENCY: Latency = Latency::new();
+pub static SQL_ERRORS: AtomicU64 = AtomicU64::new(0);
+
+/**
+ * Counters shown in /.asmfs/stats and by the metrics endpoint (metrics.rs). Everything is
+ * updated with relaxed atomics from the FUSE worker threads; the numbers are meant for
+ * operators, not for exact accounting.
+ */
+pub struct Stats {
+    pub started: Instant,
+    pub ops: [Latency; Op::ALL.len()],
+    pub bytes_read_disk: RwLock<BTreeMap<(u8, u16), AtomicU64>>, // (group_number, disk_number) => bytes
+    pub bytes_read_gap: RwLock<BTreeMap<(u8, u16), AtomicU64>>,  // -o hybrid: (group_number, disk_number) => bytes read through DBMS_DISKGROUP instead
+    pub bytes_read_dbms: AtomicU64,
+    pub errors: RwLock<BTreeMap<i32, AtomicU64>>,                // errno => replies
+    pub reconnects: AtomicU64,
+}
+
+pub struct OpTimer<'a> {
+    counters: &'a Latency,
+    start: Instant,
+}
+
+impl Drop for OpTimer<'_> {
+    fn drop(&mut self) {
+        self.counters.record(self.start.elapsed().as_micros() as u64);
+    }
+}
+
+impl Stats {
+    pub fn new() -> Stats {
+        Stats {
+            started: Instant::now(),
+            ops: [const { Latency::new() }; Op::ALL.len()],
+            bytes_read_disk: RwLock::new(BTreeMap::new()),
+            bytes_read_gap: RwLock::new(BTreeMap::new()),
+            bytes_read_dbms: AtomicU64::new(0),
+            errors: RwLock::new(BTreeMap::new()),
+            reconnects: AtomicU64::new(0),
+        }
+    }
+
+    // Latency is recorded when the returned timer goes out of scope.
+    pub fn time(&self, op: Op) -> OpTimer<'_> {
+        self.ops[op as usize].time()
+    }
+
+    // counts an error reply; used as reply.error(self.stats.error(Errno::ENOENT))
+    pub fn error(&self, errno: Errno) -> Errno {
+        let code = errno.code();
+
+        if let Some(counter) = self.errors.read().unwrap().get(&code) {
+            counter.fetch_add(1, Ordering::Relaxed);
+            return errno;
+        }
+
+        self.errors.write().unwrap().entry(code).or_default().fetch_add(1, Ordering::Relaxed);
+        errno
+    }
+
+    pub fn add_disk_bytes(&self, group_number: u8, disk_number: u16, bytes: usize) {
+        add_per_disk(&self.bytes_read_disk, group_number, disk_number, bytes);
+    }
+
+    // raw-mode file bytes on a disk this host cannot open (-o hybrid)
+    pub fn add_gap_bytes(&self, group_number: u8, disk_number: u16, bytes: usize) {
+        add_per_disk(&self.bytes_read_gap, group_number, disk_number, bytes);
+    }
+
+    pub fn add_dbms_bytes(&self, bytes: usize) {
+        self.bytes_read_dbms.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+
+        let _ = writeln!(out, "uptime_seconds {}", self.started.elapsed().as_secs());
+        let _ = writeln!(out);
+        let _ = writeln!(out, "{:<10} {:>12} {:>12} {:>12}", "op", "count", "avg_us", "max_us");
+
+        for op in Op::ALL {
+            let counters = &self.ops[op as usize];
+            let count = counters.count.load(Ordering::Relaxed);
+            let total_us = counters.total_us.load(Ordering::Relaxed);
+            let avg_us = total_us.checked_div(count).unwrap_or(0);
+
+            let _ = writeln!(out, "{:<10} {:>12} {:>12} {:>12}", op.name(), count, avg_us, counters.max_us.load(Ordering::Relaxed));
+        }
+
+        let sql_count = SQL_LATENCY.count.load(Ordering::Relaxed);
+        let sql_avg_us = SQL_LATENCY.total_us.load(Ordering::Relaxed).checked_div(sql_count).unwrap_or(0);
+        let _ = writeln!(out, "{:<10} {:>12} {:>12} {:>12}", "sql", sql_count, sql_avg_us, SQL_LATENCY.max_us.load(Ordering::Relaxed));
+
+        let _ = writeln!(out);
+        let _ = writeln!(out, "{:<6} {:<6} {:>16}", "group", "disk", "bytes_read");
+
+        for ((group_number, disk_number), bytes) in self.bytes_read_disk.read().unwrap().iter() {
+            let _ = writeln!(out, "{:<6} {:<6} {:>16}", group_number, disk_number, bytes.load(Ordering::Relaxed));
+        }
+
+        let _ = writeln!(out);
+        let _ = writeln!(out, "{:<6} {:<6} {:>16}", "group", "disk", "bytes_read_gap");
+
+        for ((group_number, disk_number), bytes) in self.bytes_read_gap.read().unwrap().iter() {
+            let _ = writeln!(out, "{:<6} {:<6} {:>16}", group_number, disk_number, bytes.load(Ordering::Relaxed));
+        }
+
+        let _ = writeln!(out);
+        let _ = writeln!(out, "bytes_read_dbms {}", self.bytes_read_dbms.load(Ordering::Relaxed));
+
+        out
+    }
+}
+
+fn add_per_disk(counters: &RwLock<BTreeMap<(u8, u16), AtomicU64>>, group_number: u8, disk_number: u16, bytes: usize) {
+    let key = (group_number, disk_number);
+
+    if let Some(counter) = counters.read().unwrap().get(&key) {
+        counter.fetch_add(bytes as u64, Ordering::Relaxed);
+        return;
+    }
+
+    counters
+        .write()
+        .unwrap()
+        .entry(key)
+        .or_default()
+        .fetch_add(bytes as u64, Ordering::Relaxed);
+}
+
+// the errors asmfs replies with; anything else by number
+pub fn errno_name(code: i32) -> String {
+    match code {
+        libc::EACCES => "EACCES".to_string(),
+        libc::ENOENT => "ENOENT".to_string(),
+        libc::ENOTDIR => "ENOTDIR".to_string(),
+        libc::EIO => "EIO".to_string(),
+        libc::EBADF => "EBADF".to_string(),
+        libc::EINVAL => "EINVAL".to_string(),
+        code => code.to_string(),
+    }
+}
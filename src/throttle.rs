@@ -0,0 +1,443 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::fs;
+use std::sync::{Mutex, RwLock};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+use log::{error, info};
+use crate::config::MountConfig;
+use crate::permissions::lookup_uid;
+
+/*
+ * Token-bucket limits on raw reads, so that copying files out of ASM does not starve the
+ * database of I/O. A rule limits bytes per second, reads per second, or both, for one scope:
+ *
+ *   # scope        bytes/s   iops
+ *   all            200M      2000
+ *   group=DATA     100M      -
+ *   uid=1001       20M       200
+ *   user=backup    50M       -
+ *
+ * `all` covers every raw read, `group=` the reads from one diskgroup and `uid=`/`user=` the reads
+ * of one user. A read waits until every rule that applies to it has tokens; each bucket holds
+ * one second worth of tokens, so short bursts pass without delay.
+ *
+ * Rules come from -o max_bps=/max_iops= (an `all` rule) and from -o throttle_file=. The file is
+ * checked for changes at most once a second and re-read when it changed, so limits can be
+ * adjusted without remounting; a file that fails to parse leaves the previous limits in place.
+ * `asmfs ctl throttle` changes single rules until the file changes next.
+ * --no-raw reads go through the database and are not throttled.
+ */
+
+const FILE_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Clone, Copy, PartialEq)]
+enum Scope {
+    All,
+    Group(u8),
+    Uid(u32),
+}
+
+struct Rule {
+    label: String, // scope as written, e.g. "group=DATA"
+    scope: Scope,
+    bytes_per_sec: Option<u64>,
+    ops_per_sec: Option<u64>,
+}
+
+struct Bucket {
+    rate: f64,
+    tokens: f64,
+    refilled: Instant,
+}
+
+impl Bucket {
+    fn new(rate: u64, now: Instant) -> Bucket {
+        Bucket { rate: rate as f64, tokens: rate as f64, refilled: now }
+    }
+
+    // takes `amount` tokens, going into debt if there are not enough; returns how long the caller has to wait for the debt to be paid back
+    fn take(&mut self, amount: f64, now: Instant) -> Duration {
+        self.tokens = (self.tokens + now.duration_since(self.refilled).as_secs_f64() * self.rate).min(self.rate);
+        self.refilled = now;
+        self.tokens -= amount;
+
+        if self.tokens >= 0.0 { Duration::ZERO } else { Duration::from_secs_f64(-self.tokens / self.rate) }
+    }
+}
+
+struct Limiter {
+    rule: Rule,
+    bytes: Option<Mutex<Bucket>>,
+    ops: Option<Mutex<Bucket>>,
+    reads: AtomicU64,
+    bytes_read: AtomicU64,
+    throttled: AtomicU64,
+    waited_us: AtomicU64,
+}
+
+impl Limiter {
+    fn new(rule: Rule) -> Limiter {
+        Limiter {
+            bytes: rule.bytes_per_sec.map(|rate| Mutex::new(Bucket::new(rate, Instant::now()))),
+            ops: rule.ops_per_sec.map(|rate| Mutex::new(Bucket::new(rate, Instant::now()))),
+            rule,
+            reads: AtomicU64::new(0),
+            bytes_read: AtomicU64::new(0),
+            throttled: AtomicU64::new(0),
+            waited_us: AtomicU64::new(0),
+        }
+    }
+
+    fn applies_to(&self, group_number: u8, uid: u32) -> bool {
+        match self.rule.scope {
+            Scope::All => true,
+            Scope::Group(number) => number == group_number,
+            Scope::Uid(rule_uid) => rule_uid == uid,
+        }
+    }
+
+    fn reserve(&self, bytes: usize) -> Duration {
+        let now = Instant::now();
+        let bytes_wait = self.bytes.as_ref().map(|bucket| bucket.lock().unwrap().take(bytes as f64, now)).unwrap_or_default();
+        let ops_wait = self.ops.as_ref().map(|bucket| bucket.lock().unwrap().take(1.0, now)).unwrap_or_default();
+        let wait = bytes_wait.max(ops_wait);
+
+        self.reads.fetch_add(1, Ordering::Relaxed);
+        self.bytes_read.fetch_add(bytes as u64, Ordering::Relaxed);
+        if !wait.is_zero() {
+            self.throttled.fetch_add(1, Ordering::Relaxed);
+            self.waited_us.fetch_add(wait.as_micros() as u64, Ordering::Relaxed);
+        }
+
+        wait
+    }
+}
+
+pub struct Throttle {
+    max_bps: u64,                          // -o max_bps/max_iops, 0 means unlimited
+    max_iops: u64,
+    throttle_file: Option<String>,
+    groups: HashMap<String, u8>,           // diskgroup name (upper case, no '+') => number, for group= rules
+    limiters: RwLock<Vec<Limiter>>,
+    file_checked: Mutex<(Instant, Option<SystemTime>)>, // last check, modification time of the file then
+}
+
+impl Throttle {
+    pub fn load(config: &MountConfig, groups: HashMap<String, u8>) -> Result<Throttle, String> {
+        let throttle = Throttle {
+            max_bps: config.max_bps,
+            max_iops: config.max_iops,
+            throttle_file: config.throttle_file.clone(),
+            groups,
+            limiters: RwLock::new(Vec::new()),
+            file_checked: Mutex::new((Instant::now(), None)),
+        };
+
+        let modified = throttle.throttle_file.as_deref().and_then(modified_time);
+        *throttle.limiters.write().unwrap() = throttle.build_limiters()?;
+        throttle.file_checked.lock().unwrap().1 = modified;
+
+        Ok(throttle)
+    }
+
+    // blocks the calling FUSE worker until the read fits every limit that applies to it
+    pub fn acquire(&self, group_number: u8, uid: u32, bytes: usize) {
+        self.reload_if_changed();
+
+        let wait = self.limiters
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|limiter| limiter.applies_to(group_number, uid))
+            .map(|limiter| limiter.reserve(bytes))
+            .max()
+            .unwrap_or_default();
+
+        if !wait.is_zero() {
+            thread::sleep(wait);
+        }
+    }
+
+    /*
+     * Sets or replaces the limit of one scope at runtime (`asmfs ctl throttle`), in the format of a
+     * throttle file line; `<scope> - -` removes it. Lasts until the throttle file changes.
+     */
+    pub fn set(&self, line: &str) -> Result<(), String> {
+        let rule = self.parse_rule(line)?;
+        let mut limiters = self.limiters.write().unwrap();
+
+        limiters.retain(|limiter| limiter.rule.scope != rule.scope);
+        if rule.bytes_per_sec.is_some() || rule.ops_per_sec.is_some() {
+            limiters.push(Limiter::new(rule));
+        }
+
+        Ok(())
+    }
+
+    // rendered as /.asmfs/throttle
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+
+        let _ = writeln!(out, "{:<20} {:>14} {:>8} {:>12} {:>16} {:>12} {:>12}", "scope", "bytes/s", "iops", "reads", "bytes", "throttled", "waited_ms");
+
+        for limiter in self.limiters.read().unwrap().iter() {
+            let _ = writeln!(
+                out,
+                "{:<20} {:>14} {:>8} {:>12} {:>16} {:>12} {:>12}",
+                limiter.rule.label,
+                limiter.rule.bytes_per_sec.map(|n| n.to_string()).unwrap_or_else(|| "-".to_string()),
+                limiter.rule.ops_per_sec.map(|n| n.to_string()).unwrap_or_else(|| "-".to_string()),
+                limiter.reads.load(Ordering::Relaxed),
+                limiter.bytes_read.load(Ordering::Relaxed),
+                limiter.throttled.load(Ordering::Relaxed),
+                limiter.waited_us.load(Ordering::Relaxed) / 1000,
+            );
+        }
+
+        out
+    }
+
+    fn reload_if_changed(&self) {
+        let Some(path) = &self.throttle_file else {
+            return;
+        };
+
+        // another worker is already checking
+        let Ok(mut checked) = self.file_checked.try_lock() else {
+            return;
+        };
+
+        if checked.0.elapsed() < FILE_CHECK_INTERVAL {
+            return;
+        }
+
+        let modified = modified_time(path);
+        checked.0 = Instant::now();
+
+        if modified == checked.1 {
+            return;
+        }
+
+        checked.1 = modified;
+
+        match self.build_limiters() {
+            Ok(limiters) => {
+                info!("Reloaded throttle file {}", path);
+                *self.limiters.write().unwrap() = limiters;
+            }
+            Err(e) => error!("Keeping previous limits: {}", e),
+        }
+    }
+
+    fn build_limiters(&self) -> Result<Vec<Limiter>, String> {
+        let mut rules = Vec::new();
+
+        if self.max_bps > 0 || self.max_iops > 0 {
+            rules.push(Rule {
+                label: "all".to_string(),
+                scope: Scope::All,
+                bytes_per_sec: Some(self.max_bps).filter(|n| *n > 0),
+                ops_per_sec: Some(self.max_iops).filter(|n| *n > 0),
+            });
+        }
+
+        if let Some(path) = &self.throttle_file {
+            let content = fs::read_to_string(path)
+                .map_err(|e| format!("Unable to read throttle file {path}: {e}"))?;
+
+            for (line_number, line) in content.lines().enumerate() {
+                let line = line.trim();
+
+                if line.is_empty() || line.starts_with('#') {
+                    continue;
+                }
+
+                rules.push(self.parse_rule(line).map_err(|e| format!("{path}:{}: {e}", line_number + 1))?);
+            }
+        }
+
+        Ok(rules.into_iter().map(Limiter::new).collect())
+    }
+
+    fn parse_rule(&self, line: &str) -> Result<Rule, String> {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+
+        let [label, bytes_per_sec, ops_per_sec] = fields[..] else {
+            return Err("expected '<scope> <bytes/s> <iops>'".to_string());
+        };
+
+        let scope = match label.split_once('=') {
+            None if label == "all" => Scope::All,
+            Some(("group", name)) => {
+                let name = name.trim_start_matches('+').to_uppercase();
+                self.groups.get(&name).copied().map(Scope::Group).ok_or_else(|| format!("unknown diskgroup '{name}'"))?
+            }
+            Some(("uid", uid)) => uid.parse().map(Scope::Uid).map_err(|_| format!("invalid uid '{uid}'"))?,
+            Some(("user", name)) => lookup_uid(name).map(Scope::Uid).ok_or_else(|| format!("unknown user '{name}'"))?,
+            _ => return Err(format!("invalid scope '{label}', expected all, group=, uid= or user=")),
+        };
+
+        let limit = |value: &str| match value {
+            "-" => Ok(None),
+            value => parse_rate(value).map(|n| Some(n).filter(|n| *n > 0)),
+        };
+
+        Ok(Rule {
+            label: label.to_string(),
+            scope,
+            bytes_per_sec: limit(bytes_per_sec)?,
+            ops_per_sec: limit(ops_per_sec)?,
+        })
+    }
+}
+
+fn modified_time(path: &str) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+// 200M => 209715200; K, M and G are powers of 1024
+pub fn parse_rate(value: &str) -> Result<u64, String> {
+    let (digits, multiplier) = match value.char_indices().last() {
+        Some((index, 'k' | 'K')) => (&value[..index], 1u64 << 10),
+        Some((index, 'm' | 'M')) => (&value[..index], 1u64 << 20),
+        Some((index, 'g' | 'G')) => (&value[..index], 1u64 << 30),
+        _ => (value, 1),
+    };
+
+    digits
+        .parse::<u64>()
+        .ok()
+        .and_then(|n| n.checked_mul(multiplier))
+        .ok_or_else(|| format!("'{value}' is not a number (K, M and G suffixes are allowed)"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn throttle() -> Throttle {
+        Throttle {
+            max_bps: 0,
+            max_iops: 0,
+            throttle_file: None,
+            groups: HashMap::from([("DATA".to_string(), 1), ("FRA".to_string(), 2)]),
+            limiters: RwLock::new(Vec::new()),
+            file_checked: Mutex::new((Instant::now(), None)),
+        }
+    }
+
+    fn scopes(throttle: &Throttle) -> Vec<String> {
+        throttle.limiters.read().unwrap().iter().map(|limiter| limiter.rule.label.clone()).collect()
+    }
+
+    #[test]
+    fn debt_is_paid_back_at_the_rate() {
+        let start = Instant::now();
+        let mut bucket = Bucket::new(1000, start);
+
+        assert_eq!(bucket.take(1000.0, start), Duration::ZERO);
+        assert_eq!(bucket.take(500.0, start), Duration::from_millis(500));
+        assert_eq!(bucket.take(1500.0, start), Duration::from_secs(2));
+
+        // the 2000 tokens of debt are paid back after two seconds
+        assert_eq!(bucket.take(0.0, start + Duration::from_secs(2)), Duration::ZERO);
+    }
+
+    #[test]
+    fn burst_is_one_second_of_tokens() {
+        let start = Instant::now();
+        let mut bucket = Bucket::new(1000, start);
+
+        // a minute idle still leaves only one second worth of tokens
+        let later = start + Duration::from_secs(60);
+        assert_eq!(bucket.take(1000.0, later), Duration::ZERO);
+        assert_eq!(bucket.take(250.0, later), Duration::from_millis(250));
+    }
+
+    #[test]
+    fn rates_take_binary_suffixes() {
+        assert_eq!(parse_rate("4096"), Ok(4096));
+        assert_eq!(parse_rate("8k"), Ok(8 << 10));
+        assert_eq!(parse_rate("200M"), Ok(200 << 20));
+        assert_eq!(parse_rate("2G"), Ok(2 << 30));
+        assert_eq!(parse_rate("0"), Ok(0));
+
+        assert!(parse_rate("").is_err());
+        assert!(parse_rate("M").is_err());
+        assert!(parse_rate("1.5M").is_err());
+        assert!(parse_rate("-1").is_err());
+        assert!(parse_rate("10T").is_err());
+    }
+
+    #[test]
+    fn rates_that_overflow_are_rejected() {
+        assert_eq!(parse_rate("17179869183G"), Ok(17179869183 << 30));
+        assert!(parse_rate("17179869184G").is_err());
+        assert!(parse_rate("17592186044416M").is_err());
+        assert!(parse_rate("18014398509481984K").is_err());
+        assert!(parse_rate("18446744073709551616").is_err());
+    }
+
+    #[test]
+    fn rules_are_parsed() {
+        let throttle = throttle();
+
+        let rule = throttle.parse_rule("group=+data  100M  -").unwrap();
+        assert!(rule.scope == Scope::Group(1));
+        assert_eq!(rule.bytes_per_sec, Some(100 << 20));
+        assert_eq!(rule.ops_per_sec, None);
+
+        let rule = throttle.parse_rule("uid=1001 0 200").unwrap();
+        assert!(rule.scope == Scope::Uid(1001));
+        assert_eq!(rule.bytes_per_sec, None);
+        assert_eq!(rule.ops_per_sec, Some(200));
+
+        assert!(throttle.parse_rule("all 1M 100").unwrap().scope == Scope::All);
+        assert!(throttle.parse_rule("user=root 1M -").unwrap().scope == Scope::Uid(0));
+
+        assert!(throttle.parse_rule("all 1M").is_err());
+        assert!(throttle.parse_rule("all 1M 100 5").is_err());
+        assert!(throttle.parse_rule("group=RECO 1M -").is_err());
+        assert!(throttle.parse_rule("uid=oracle 1M -").is_err());
+        assert!(throttle.parse_rule("pid=1 1M -").is_err());
+        assert!(throttle.parse_rule("all 1X -").is_err());
+    }
+
+    #[test]
+    fn set_replaces_and_removes_rules() {
+        let throttle = throttle();
+
+        throttle.set("all 200M 2000").unwrap();
+        throttle.set("group=DATA 100M -").unwrap();
+        assert_eq!(scopes(&throttle), ["all", "group=DATA"]);
+
+        throttle.set("all 50M -").unwrap();
+        assert_eq!(scopes(&throttle), ["group=DATA", "all"]);
+        assert_eq!(throttle.limiters.read().unwrap()[1].rule.bytes_per_sec, Some(50 << 20));
+
+        throttle.set("group=+data - -").unwrap();
+        assert_eq!(scopes(&throttle), ["all"]);
+
+        assert!(throttle.set("group=RECO - -").is_err());
+        assert_eq!(scopes(&throttle), ["all"]);
+    }
+
+    #[test]
+    fn reads_wait_for_every_rule_that_applies() {
+        let throttle = throttle();
+        throttle.set("group=DATA 1M -").unwrap();
+        throttle.set("uid=1001 - 1").unwrap();
+
+        let limiters = throttle.limiters.read().unwrap();
+        assert!(limiters[0].applies_to(1, 1002));
+        assert!(!limiters[0].applies_to(2, 1001));
+        assert!(limiters[1].applies_to(2, 1001));
+
+        // the first read of a second passes, the next one waits for the iops bucket
+        assert_eq!(limiters[1].reserve(8192), Duration::ZERO);
+        assert!(limiters[1].reserve(8192) > Duration::from_millis(900));
+        assert_eq!(limiters[1].throttled.load(Ordering::Relaxed), 1);
+    }
+}
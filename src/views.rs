@@ -0,0 +1,219 @@
+use fuser::{FileAttr, FileType, INodeNo};
+use oracle::{Error, ErrorKind};
+use log::warn;
+use crate::fuse::AsmFS;
+use crate::inode::{Inode, ENTRY_VIEW_BY_DATABASE, ENTRY_VIEW_BY_NUMBER, ENTRY_VIEW_BY_TYPE, VIEW_DIR};
+
+/*
+ * Virtual directories offered inside every diskgroup, next to the alias hierarchy:
+ *
+ *   +DATA/.by-number/<file_number>.<incarnation>
+ *   +DATA/.by-type/<TYPE>/<system alias name>
+ *   +DATA/.by-database/<DB>/<TYPE>/<system alias name>
+ *
+ * Entries resolve to the inode of the file's system alias, so they are the very same files as
+ * seen in the alias tree. Files whose aliases were lost get an inode of their own inside
+ * .by-number/ and are not part of the other views. <DB> is any top-level directory of the group.
+ */
+
+const DISKGROUP_VIEWS: &[(u32, &str)] = &[ // entry number, directory name
+    (ENTRY_VIEW_BY_NUMBER, ".by-number"),
+    (ENTRY_VIEW_BY_TYPE, ".by-type"),
+    (ENTRY_VIEW_BY_DATABASE, ".by-database"),
+];
+
+// v$asm_file.type values; file type id = index + 1. Append only: the id is part of inode numbers.
+const ASM_FILE_TYPES: &[&str] = &[
+    "ARCHIVELOG",
+    "ASMPARAMETERFILE",
+    "ASMVDRL",
+    "ASMVOL",
+    "AUDIT_SPILLFILES",
+    "AUTOBACKUP",
+    "AUTOLOGIN_KEY_STORE",
+    "BACKUPSET",
+    "CHANGETRACKING",
+    "CONTAINER",
+    "CONTROLFILE",
+    "DATAFILE",
+    "DATAGUARDCONFIG",
+    "DUMPSET",
+    "FLASHBACK",
+    "FLASHFILE",
+    "INCR XTRANSPORT BACKUP",
+    "KEY_STORE",
+    "OCRBACKUP",
+    "OCRFILE",
+    "ONLINELOG",
+    "PARAMETERFILE",
+    "PASSWORD",
+    "TEMPFILE",
+    "VOTINGFILE",
+    "XTRANSPORT",
+    "XTRANSPORT BACKUP",
+];
+
+fn file_type_id(file_type: &str) -> Option<u32> {
+    ASM_FILE_TYPES
+        .iter()
+        .position(|t| *t == file_type)
+        .map(|index| index as u32 + 1)
+}
+
+fn file_type_name(type_id: u32) -> Option<&'static str> {
+    ASM_FILE_TYPES.get((type_id as usize).checked_sub(1)?).copied()
+}
+
+pub fn view_entries(group_number: u8) -> Vec<(u64, FileType, String)> {
+    DISKGROUP_VIEWS
+        .iter()
+        .map(|(view, name)| (Inode::from_view(group_number, *view, VIEW_DIR).get_ino(), FileType::Directory, name.to_string()))
+        .collect()
+}
+
+// views exist only directly below a diskgroup directory
+pub fn view_lookup(parent_ino: u64, name: &str) -> Option<u64> {
+    let parent = Inode::from_ino(parent_ino);
+
+    if !parent.is_disk_group() {
+        return None;
+    }
+
+    DISKGROUP_VIEWS
+        .iter()
+        .find(|(_, view_name)| *view_name == name)
+        .map(|(view, _)| Inode::from_view(parent.get_group_number(), *view, VIEW_DIR).get_ino())
+}
+
+fn unknown_view(ino: u64) -> Error {
+    Error::new(ErrorKind::Other, format!("asmfs; unknown view inode {}", ino))
+}
+
+// .by-database/ payload: entry_number of the database directory << 8 | file type id (0 for the database directory itself)
+fn split_database_payload(group_number: u8, payload: u32) -> (u32, u32) {
+    let db_reference_index = ((group_number as u32) << 24) | (payload >> 8);
+    (db_reference_index, payload & 0xFF)
+}
+
+impl AsmFS {
+    pub(crate) fn view_attr(&self, ino: u64) -> Result<FileAttr, Error> {
+        let inode = Inode::from_ino(ino);
+        let group_number = inode.get_group_number();
+        let ora = self.ora()?;
+
+        match (inode.get_view(), inode.get_alias_index()) {
+            (_, VIEW_DIR) if DISKGROUP_VIEWS.iter().any(|(view, _)| *view == inode.get_view()) => {
+                // same shape as the diskgroup directory itself, and proves the group is mounted
+                ora.query_asm_diskgroup_ent_ino(ino)
+            }
+            (ENTRY_VIEW_BY_NUMBER, _) => ora.query_by_number_ent_ino(ino),
+            (ENTRY_VIEW_BY_TYPE, type_id) if file_type_name(type_id).is_some() => ora.query_asm_diskgroup_ent_ino(ino),
+            (ENTRY_VIEW_BY_DATABASE, payload) => {
+                let (db_reference_index, type_id) = split_database_payload(group_number, payload);
+
+                if type_id != 0 && file_type_name(type_id).is_none() {
+                    return Err(unknown_view(ino));
+                }
+
+                ora.query_asm_directory_name(db_reference_index)?;
+                ora.query_asm_diskgroup_ent_ino(ino)
+            }
+            _ => Err(unknown_view(ino)),
+        }
+    }
+
+    pub(crate) fn view_parent_ino(&self, ino: u64) -> Result<INodeNo, Error> {
+        let inode = Inode::from_ino(ino);
+        let group_number = inode.get_group_number();
+
+        let parent = match (inode.get_view(), inode.get_alias_index()) {
+            (_, VIEW_DIR) => Inode::from_group_number(group_number),
+            (ENTRY_VIEW_BY_DATABASE, payload) if payload & 0xFF != 0 => Inode::from_view(group_number, ENTRY_VIEW_BY_DATABASE, payload & !0xFF),
+            (view, _) => Inode::from_view(group_number, view, VIEW_DIR),
+        };
+
+        Ok(INodeNo(parent.get_ino()))
+    }
+
+    pub(crate) fn view_children(&self, ino: u64) -> Result<Vec<(u64, FileType, String)>, Error> {
+        let inode = Inode::from_ino(ino);
+        let group_number = inode.get_group_number();
+        let ora = self.ora()?;
+
+        match (inode.get_view(), inode.get_alias_index()) {
+            (ENTRY_VIEW_BY_NUMBER, VIEW_DIR) => ora.query_by_number_vec(group_number),
+            (ENTRY_VIEW_BY_TYPE, VIEW_DIR) => {
+                let types = ora.query_file_types(group_number, None)?;
+                Ok(type_directories(group_number, ENTRY_VIEW_BY_TYPE, 0, types))
+            }
+            (ENTRY_VIEW_BY_TYPE, type_id) => {
+                let file_type = file_type_name(type_id).ok_or_else(|| unknown_view(ino))?;
+                ora.query_system_alias_by_type_vec(group_number, file_type, None, None)
+            }
+            (ENTRY_VIEW_BY_DATABASE, VIEW_DIR) => {
+                let group_ino = Inode::from_group_number(group_number).get_ino();
+                let databases = ora
+                    .query_asm_alias_vec(group_ino)?
+                    .into_iter()
+                    .filter(|(_, kind, _)| *kind == FileType::Directory)
+                    .map(|(db_ino, kind, name)| {
+                        let db_entry = Inode::from_ino(db_ino).get_reference_index() & 0x00FF_FFFF;
+                        (Inode::from_view(group_number, ENTRY_VIEW_BY_DATABASE, db_entry << 8).get_ino(), kind, name)
+                    })
+                    .collect();
+                Ok(databases)
+            }
+            (ENTRY_VIEW_BY_DATABASE, payload) => {
+                let (db_reference_index, type_id) = split_database_payload(group_number, payload);
+
+                if type_id == 0 {
+                    let types = ora.query_file_types(group_number, Some(db_reference_index))?;
+                    Ok(type_directories(group_number, ENTRY_VIEW_BY_DATABASE, payload, types))
+                } else {
+                    let file_type = file_type_name(type_id).ok_or_else(|| unknown_view(ino))?;
+                    ora.query_system_alias_by_type_vec(group_number, file_type, Some(db_reference_index), None)
+                }
+            }
+            _ => Err(unknown_view(ino)),
+        }
+    }
+
+    pub(crate) fn view_lookup_child(&self, parent_ino: u64, name: &str) -> Result<FileAttr, Error> {
+        let inode = Inode::from_ino(parent_ino);
+        let group_number = inode.get_group_number();
+
+        let child_ino = match (inode.get_view(), inode.get_alias_index()) {
+            (ENTRY_VIEW_BY_NUMBER, VIEW_DIR) => return self.ora()?.query_by_number_ent(group_number, name),
+            (ENTRY_VIEW_BY_TYPE, type_id) if type_id != VIEW_DIR => {
+                let file_type = file_type_name(type_id).ok_or_else(|| unknown_view(parent_ino))?;
+                self.ora()?.query_system_alias_by_type_vec(group_number, file_type, None, Some(name))?.first().map(|child| child.0)
+            }
+            (ENTRY_VIEW_BY_DATABASE, payload) if payload != VIEW_DIR && payload & 0xFF != 0 => {
+                let (db_reference_index, type_id) = split_database_payload(group_number, payload);
+                let file_type = file_type_name(type_id).ok_or_else(|| unknown_view(parent_ino))?;
+                self.ora()?.query_system_alias_by_type_vec(group_number, file_type, Some(db_reference_index), Some(name))?.first().map(|child| child.0)
+            }
+            // the remaining directories are short lists of type or database names
+            _ => self.view_children(parent_ino)?.into_iter().find(|child| self.name_matches(&child.2, name)).map(|child| child.0),
+        };
+
+        match child_ino {
+            Some(ino) if Inode::from_ino(ino).is_view() => self.view_attr(ino),
+            Some(ino) => self.ora()?.query_asm_alias_ent_ino(ino),
+            None => Err(Error::new(ErrorKind::Other, format!("asmfs; '{}' not found in view inode {}", name, parent_ino))),
+        }
+    }
+}
+
+fn type_directories(group_number: u8, view: u32, payload: u32, types: Vec<String>) -> Vec<(u64, FileType, String)> {
+    types
+        .into_iter()
+        .filter_map(|file_type| match file_type_id(&file_type) {
+            Some(type_id) => Some((Inode::from_view(group_number, view, payload | type_id).get_ino(), FileType::Directory, file_type)),
+            None => {
+                warn!("v$asm_file.type '{}' is not known to asmfs; such files are not shown in {}", file_type, if view == ENTRY_VIEW_BY_TYPE { ".by-type" } else { ".by-database" });
+                None
+            }
+        })
+        .collect()
+}
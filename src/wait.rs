@@ -0,0 +1,120 @@
+use std::env;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use log::info;
+use crate::config::MountConfig;
+use crate::fuse::connect_metadata;
+use crate::notify;
+
+/*
+ * --wait-for-asm[=TIMEOUT] (or -o wait_for_asm[=TIMEOUT]): at boot asmfs may start before Oracle
+ * Restart has started ASM. Instead of failing, asmfs then waits for
+ *
+ *   1. an asm_pmon_+ASM* process, see oraenv.rs (not with --conn, where ASM runs elsewhere)
+ *   2. v$instance.status = STARTED
+ *   3. the diskgroups given with --diskgroups, or at least one diskgroup, to be mounted
+ *
+ * before mounting FUSE. TIMEOUT is in seconds, or with an s, m or h suffix; without it asmfs
+ * waits for as long as it takes.
+ */
+
+pub const POLL_INTERVAL: Duration = Duration::from_secs(5);
+const DEADLINE_ENV: &str = "ASMFS_WAIT_UNTIL"; // so the re-exec of oraenv.rs does not restart the clock
+
+#[derive(Clone, Copy)]
+pub struct Deadline {
+    until: Option<SystemTime>, // None waits forever
+}
+
+impl Deadline {
+    pub fn new(timeout: Option<Duration>) -> Deadline {
+        let inherited = env::var(DEADLINE_ENV)
+            .ok()
+            .and_then(|secs| secs.parse().ok())
+            .map(|secs| UNIX_EPOCH + Duration::from_secs(secs));
+
+        Deadline { until: timeout.map(|timeout| inherited.unwrap_or_else(|| SystemTime::now() + timeout)) }
+    }
+
+    pub fn expired(&self) -> bool {
+        self.until.is_some_and(|until| SystemTime::now() >= until)
+    }
+
+    // for DEADLINE_ENV, set by oraenv.rs before it re-executes asmfs
+    pub fn env_var(&self) -> Option<(&'static str, String)> {
+        let secs = self.until?.duration_since(UNIX_EPOCH).ok()?.as_secs();
+        Some((DEADLINE_ENV, secs.to_string()))
+    }
+}
+
+// "" => forever, "90" => 90 s, "10m" => 600 s
+pub fn parse_timeout(value: &str) -> Result<Option<Duration>, String> {
+    if value.is_empty() {
+        return Ok(None);
+    }
+
+    let (digits, multiplier) = match value.char_indices().last() {
+        Some((index, 's')) => (&value[..index], 1),
+        Some((index, 'm')) => (&value[..index], 60),
+        Some((index, 'h')) => (&value[..index], 3600),
+        _ => (value, 1),
+    };
+
+    digits
+        .parse::<u64>()
+        .ok()
+        .and_then(|n| n.checked_mul(multiplier))
+        .map(|secs| Some(Duration::from_secs(secs)))
+        .ok_or_else(|| format!("invalid timeout '{value}', expected seconds or a number with an s, m or h suffix"))
+}
+
+// steps 2 and 3; step 1 happens in oraenv.rs, before the Oracle client is loaded
+pub fn wait_until_ready(config: &MountConfig, deadline: Deadline) -> Result<(), String> {
+    let mut reported = String::new();
+
+    loop {
+        let reason = match asm_not_ready(config) {
+            Ok(()) => return Ok(()),
+            Err(reason) => reason,
+        };
+
+        if deadline.expired() {
+            return Err(format!("ASM not ready before the --wait-for-asm timeout: {reason}"));
+        }
+
+        // once per state, not once per poll
+        if reason != reported {
+            info!("Waiting for ASM: {}", reason);
+            notify::status(&format!("Waiting for ASM: {reason}"));
+            reported = reason;
+        }
+
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+fn asm_not_ready(config: &MountConfig) -> Result<(), String> {
+    let ora = connect_metadata(config, 0)?;
+
+    let status = ora.query_instance_status().map_err(|e| format!("Unable to query instance status: {e}"))?;
+    if status != "STARTED" {
+        return Err(format!("instance is {status}"));
+    }
+
+    let mounted: Vec<String> = ora
+        .query_asm_diskgroup_details()
+        .map_err(|e| format!("Unable to query diskgroups: {e}"))?
+        .into_iter()
+        .filter(|group| group.state == "MOUNTED" || group.state == "CONNECTED")
+        .map(|group| group.name.trim_start_matches('+').to_string())
+        .collect();
+
+    match &config.diskgroups {
+        Some(wanted) => {
+            let missing: Vec<String> = wanted.iter().filter(|name| !mounted.contains(name)).map(|name| format!("+{name}")).collect();
+            if missing.is_empty() { Ok(()) } else { Err(format!("diskgroups not mounted: {}", missing.join(", "))) }
+        }
+        None if mounted.is_empty() => Err("no diskgroup mounted".to_string()),
+        None => Ok(()),
+    }
+}